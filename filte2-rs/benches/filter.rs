@@ -0,0 +1,86 @@
+// `cargo bench`, or `cargo bench -- FILE` to time the filters on a real log
+//
+// times each filter as parsed and after `Filter::compile`, on the same lines
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use filte2_rs::{expr, Filter};
+
+const FILTERS: &[&str] = &[
+    "+ERROR | +WARN | +panicked | +timeout | +refused | +denied",
+    r#"."user=\d+" | ."id=[a-f0-9]{8}" | ."took \d{4,}ms" | .^\[debug\]"#,
+    "-healthcheck & -/metrics & -favicon & i+200 & i.GET",
+    "c+error | c+warn | f3>500 & ^[",
+    "(+ERROR | +WARN) & !(+retry | +healthcheck | +/metrics)",
+];
+
+// mostly uneventful lines, like most logs
+const LEVELS: &[&str] = &[
+    "[info]", "[info]", "[info]", "[info]", "[info]", "[debug]", "[WARN]", "[ERROR]",
+];
+// the rarer words at the end
+const WORDS: &str = "GET POST /api/users /api/orders /static/app.js request served in session
+    started closed cache hit miss for client 10.0.0.1 10.0.3.7 upstream ok done 200 304 bytes
+    sent /metrics healthcheck retry timeout user=42 id=deadbeef";
+
+fn main() {
+    let file = std::env::args().skip(1).find(|a| !a.starts_with("--"));
+    let input = match file {
+        Some(path) => std::fs::read_to_string(path).expect("readable input"),
+        None => generate(200_000),
+    };
+    let lines: Vec<&str> = input.lines().collect();
+    let mb = input.len() as f64 / 1e6;
+    println!("{} lines, {mb:.1} MB", lines.len());
+    for src in FILTERS {
+        let plain = expr::parse(src).expect("valid filter");
+        let compiled = expr::parse(src).expect("valid filter").compile();
+        let (n, plain) = time(&plain, &lines);
+        let (m, compiled) = time(&compiled, &lines);
+        assert_eq!(n, m, "compiling changed the matches of {src}");
+        println!("\n{src}\n  {n} matches");
+        for (name, t) in [("parsed", plain), ("compiled", compiled)] {
+            let secs = t.as_secs_f64();
+            println!("  {name:>8}: {:>8.1?} {:>8.1} MB/s", t, mb / secs);
+        }
+    }
+}
+
+// the best of a few runs
+fn time(filter: &Filter, lines: &[&str]) -> (usize, Duration) {
+    let mut best = Duration::MAX;
+    let mut matches = 0;
+    for _ in 0..5 {
+        let start = Instant::now();
+        matches = lines
+            .iter()
+            .filter(|l| filter.compare(black_box(l)))
+            .count();
+        best = best.min(start.elapsed());
+    }
+    (matches, best)
+}
+
+// log-like lines from a fixed seed
+fn generate(n: usize) -> String {
+    let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut next = move |bound: usize| {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed as usize % bound
+    };
+    let words: Vec<&str> = WORDS.split_whitespace().collect();
+    let mut out = String::new();
+    for i in 0..n {
+        out += LEVELS[next(LEVELS.len())];
+        out += &format!(" {i} {} ", next(1000));
+        for _ in 0..8 + next(8) {
+            out += words[next(words.len()).min(next(words.len()))];
+            out.push(' ');
+        }
+        out += &format!("took {}ms\n", next(5000));
+    }
+    out
+}
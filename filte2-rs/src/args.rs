@@ -0,0 +1,149 @@
+use std::path::PathBuf;
+
+use crate::fields::Split;
+use crate::output::{OutputOptions, Transform};
+use crate::FilteError;
+
+#[derive(Debug, Default)]
+pub struct Args {
+    // -e, all filters in a single expression
+    pub expr: Option<String>,
+    // the filters as separate words, `and[ ... ]` groups included, the words
+    // after the first whole filter are files
+    pub filters: Vec<String>,
+    // files to read instead of stdin, with -e
+    pub files: Vec<PathBuf>,
+    // -r, directories to read every file under
+    pub recursive: Vec<PathBuf>,
+    // --paths, an expression the paths of the files read have to match
+    pub paths: Option<String>,
+    pub split: Split,
+    // --header, the first line is printed as it is instead of filtered
+    pub header: bool,
+    pub output: OutputOptions,
+    // flush every line, for slow input piped on to another program
+    pub line_buffered: bool,
+    // print the filter as a tree instead of running it
+    pub explain: bool,
+    // --filters, where `@name` filters are defined instead of the default file
+    pub definitions: Option<PathBuf>,
+}
+
+impl Args {
+    // options come before the filters, `--` ends them
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, FilteError> {
+        let mut out = Args::default();
+        let mut args = args.into_iter().peekable();
+        let mut delimiter = None;
+        let mut csv = false;
+        while let Some(arg) = args.next_if(|a| is_option(a)) {
+            match arg.as_str() {
+                "--" => break,
+                "-e" | "--expr" => out.expr = Some(value(&arg, args.next())?),
+                "-d" | "--delimiter" => delimiter = Some(single_char(&value(&arg, args.next())?)?),
+                "--csv" => csv = true,
+                "--header" => out.header = true,
+                "--highlight" => out.output.transform = Transform::Highlight,
+                "-o" | "--only-matching" => out.output.transform = Transform::OnlyMatching,
+                "--replace" => out.output.transform = Transform::Replace(value(&arg, args.next())?),
+                "-A" => out.output.after = lines(&arg, args.next())?,
+                "-B" => out.output.before = lines(&arg, args.next())?,
+                "-C" => {
+                    out.output.after = lines(&arg, args.next())?;
+                    out.output.before = out.output.after;
+                }
+                "-c" | "--count" => out.output.count = true,
+                "-0" | "--null" => out.output.null = true,
+                "--rank" => out.output.rank = true,
+                "--line-buffered" => out.line_buffered = true,
+                "--explain" => out.explain = true,
+                "-r" | "--recursive" => out.recursive.push(value(&arg, args.next())?.into()),
+                "--paths" => out.paths = Some(value(&arg, args.next())?),
+                "--filters" => out.definitions = Some(value(&arg, args.next())?.into()),
+                // -d, -d: and the like
+                _ => delimiter = Some(single_char(&arg[2..])?),
+            }
+        }
+        out.split = match (delimiter, csv) {
+            (d, true) => Split::Csv(d.unwrap_or(',')),
+            (Some(d), false) => Split::Delimiter(d),
+            (None, false) => Split::Whitespace,
+        };
+        match out.expr {
+            Some(_) => out.files = args.map(PathBuf::from).collect(),
+            None => out.filters = args.collect(),
+        }
+        Ok(out)
+    }
+}
+
+fn is_option(arg: &str) -> bool {
+    matches!(
+        arg,
+        "--" | "-e"
+            | "--expr"
+            | "-d"
+            | "--delimiter"
+            | "--csv"
+            | "--header"
+            | "--highlight"
+            | "-o"
+            | "--only-matching"
+            | "--replace"
+            | "-A"
+            | "-B"
+            | "-C"
+            | "-c"
+            | "--count"
+            | "-0"
+            | "--null"
+            | "--rank"
+            | "--line-buffered"
+            | "--explain"
+            | "-r"
+            | "--recursive"
+            | "--paths"
+            | "--filters"
+    ) || arg.starts_with("-d")
+}
+
+fn value(arg: &str, value: Option<String>) -> Result<String, FilteError> {
+    value.ok_or(FilteError::MissingArgValue(arg.to_string()))
+}
+
+fn lines(arg: &str, value: Option<String>) -> Result<usize, FilteError> {
+    let value = self::value(arg, value)?;
+    value
+        .parse()
+        .map_err(|_| FilteError::InvalidArgValue(arg.to_string(), value))
+}
+
+// anything starting with -d is a delimiter, even an exclude filter like -dz
+fn single_char(value: &str) -> Result<char, FilteError> {
+    let mut chars = value.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(c),
+        _ => Err(FilteError::InvalidDelimiter(value.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, FilteError> {
+        Args::parse(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn delimiters() {
+        assert_eq!(parse(&["-d:", "+x"]).unwrap().split, Split::Delimiter(':'));
+        assert_eq!(parse(&["-d", ";", "+x"]).unwrap().split, Split::Delimiter(';'));
+        assert_eq!(parse(&["--csv", "-d;", "+x"]).unwrap().split, Split::Csv(';'));
+        assert!(matches!(parse(&["-debug"]), Err(FilteError::InvalidDelimiter(d)) if d == "ebug"));
+        assert!(matches!(parse(&["-d", "::", "+x"]), Err(FilteError::InvalidDelimiter(_))));
+        // filters that look like options
+        assert_eq!(parse(&["--", "-dz"]).unwrap().filters, ["-dz"]);
+        assert_eq!(parse(&["edz"]).unwrap().filters, ["edz"]);
+    }
+}
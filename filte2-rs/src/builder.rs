@@ -0,0 +1,170 @@
+use crate::*;
+
+// filters built in code instead of parsed, for other tools' include/exclude options
+//
+//     use filte2_rs::builder::*;
+//     let f = Filter::any([ends(".rs").build()?, ends(".toml").build()?])
+//         .and(!starts("target/").build()?);
+//
+// raw filters go through the same checks as parsed ones when built
+#[derive(Debug, Clone)]
+pub struct RawBuilder {
+    mode: char,
+    pattern: String,
+    invert: bool,
+    ignore_case: bool,
+}
+
+pub fn is(pattern: &str) -> RawBuilder {
+    RawBuilder::new('=', pattern)
+}
+
+pub fn starts(pattern: &str) -> RawBuilder {
+    RawBuilder::new('^', pattern)
+}
+
+pub fn ends(pattern: &str) -> RawBuilder {
+    RawBuilder::new('$', pattern)
+}
+
+pub fn includes(pattern: &str) -> RawBuilder {
+    RawBuilder::new('+', pattern)
+}
+
+pub fn excludes(pattern: &str) -> RawBuilder {
+    includes(pattern).invert()
+}
+
+pub fn regex(pattern: &str) -> RawBuilder {
+    RawBuilder::new('.', pattern)
+}
+
+pub fn glob(pattern: &str) -> RawBuilder {
+    RawBuilder::new('?', pattern)
+}
+
+// the text as a number
+pub fn number(cmp: Cmp) -> RawBuilder {
+    match cmp {
+        Cmp::Equal(x) => number(Cmp::Range(x, x)),
+        Cmp::Greater(x) => RawBuilder::new('>', &x.to_string()),
+        Cmp::Less(x) => RawBuilder::new('<', &x.to_string()),
+        Cmp::Range(..) => RawBuilder::new('~', &cmp.to_string()[1..]),
+    }
+}
+
+// the length of the text in chars
+pub fn length(cmp: Cmp) -> RawBuilder {
+    RawBuilder::new('l', &cmp.to_string())
+}
+
+// within `max` edits of the pattern, somewhere in the text
+pub fn fuzzy(pattern: &str, max: usize) -> RawBuilder {
+    RawBuilder::new('%', &format!("{max}:{pattern}"))
+}
+
+// the pattern's chars in order
+pub fn subsequence(pattern: &str) -> RawBuilder {
+    RawBuilder::new('*', pattern)
+}
+
+impl RawBuilder {
+    fn new(mode: char, pattern: &str) -> Self {
+        RawBuilder {
+            mode,
+            pattern: pattern.to_string(),
+            invert: false,
+            ignore_case: false,
+        }
+    }
+
+    pub fn invert(mut self) -> Self {
+        self.invert = !self.invert;
+        self
+    }
+
+    pub fn ignore_case(mut self) -> Self {
+        self.ignore_case = true;
+        self
+    }
+
+    pub fn build(self) -> Result<Filter, FilteError> {
+        let mut tx = String::new();
+        if self.invert {
+            tx.push('i');
+        }
+        if self.ignore_case {
+            tx.push('c');
+        }
+        tx.push(self.mode);
+        tx += &self.pattern;
+        RawFilter::try_from(tx).map(Filter::Raw)
+    }
+}
+
+impl Filter {
+    pub fn all(filters: impl IntoIterator<Item = Filter>) -> Filter {
+        Filter::And(filters.into_iter().collect())
+    }
+
+    pub fn any(filters: impl IntoIterator<Item = Filter>) -> Filter {
+        Filter::Or(filters.into_iter().collect())
+    }
+
+    // `self & other`, joining an existing `and` instead of nesting it
+    pub fn and(self, other: Filter) -> Filter {
+        match self {
+            Filter::And(mut rs) => {
+                rs.push(other);
+                Filter::And(rs)
+            }
+            r => Filter::And(vec![r, other]),
+        }
+    }
+
+    // `self | other`, joining an existing `or` instead of nesting it
+    pub fn or(self, other: Filter) -> Filter {
+        match self {
+            Filter::Or(mut rs) => {
+                rs.push(other);
+                Filter::Or(rs)
+            }
+            r => Filter::Or(vec![r, other]),
+        }
+    }
+
+    // the filter applied to a single field
+    pub fn on(self, column: Column) -> Filter {
+        Filter::Field(column, Box::new(self))
+    }
+}
+
+impl std::ops::Not for Filter {
+    type Output = Filter;
+    fn not(self) -> Filter {
+        Filter::Not(Box::new(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_like_parsed() {
+        let f = Filter::any([ends(".rs").build().unwrap(), ends(".toml").build().unwrap()])
+            .and(!starts("target/").build().unwrap())
+            .and(length(Cmp::Less(20.0)).build().unwrap());
+        let parsed = expr::parse("($.rs | $.toml) & !^target/ & l<20").unwrap();
+        for line in ["src/main.rs", "Cargo.toml", "target/main.rs", "a.md", "a/very/long/path.rs"] {
+            assert_eq!(f.compare(line), parsed.compare(line), "{line}");
+        }
+
+        let f = regex("^AB").ignore_case().build().unwrap();
+        assert!(f.compare("abc"));
+        let f = number(Cmp::Range(1.0, f64::INFINITY)).build().unwrap().on(Column::Index(2));
+        assert!(f.compare_record(&Record::split("x 3", fields::Split::Whitespace, &[])));
+        assert!(excludes("x").build().unwrap().compare("abc"));
+        assert!(regex("(").build().is_err());
+    }
+}
@@ -0,0 +1,220 @@
+use std::sync::Arc;
+
+use regex::{Regex, RegexBuilder, RegexSet};
+
+use crate::*;
+
+// sibling filters merged into a single automaton, matches when any of them does
+// (or none of them, for inverted ones merged from an `and`)
+#[derive(Debug, Clone)]
+pub struct Multi {
+    matcher: Matcher,
+    pub(crate) invert: bool,
+    // the merged filters, used for spans
+    pub(crate) filters: Vec<RawFilter>,
+}
+
+#[derive(Debug, Clone)]
+enum Matcher {
+    // an alternation of the escaped literals, searched with the regex crate's
+    // Aho-Corasick and SIMD prefilters, which beat a separate automaton per
+    // line on short lines (see benches/filter.rs)
+    Literals(Regex),
+    Regexes(RegexSet),
+}
+
+impl Filter {
+    // a filter matching the same lines, faster on long inputs:
+    // `+a | +b | +c` share one literal matcher, `.x | .y` one RegexSet,
+    // `-a & -b` and `i.x & i.y` the same, inverted, and the children of
+    // groups are reordered so cheaper ones short circuit the rest
+    pub fn compile(self) -> Filter {
+        match self {
+            Filter::Raw(_) | Filter::Multi(_) => self,
+            Filter::Or(rs) => group(rs, false, Filter::Or),
+            Filter::And(rs) => group(rs, true, Filter::And),
+            Filter::Not(r) => Filter::Not(Box::new(r.compile())),
+            Filter::Field(col, r) => Filter::Field(col, Box::new(r.compile())),
+            Filter::Named(name, r) => {
+                Filter::Named(name, Arc::new(Arc::unwrap_or_clone(r).compile()))
+            }
+        }
+    }
+
+    // a rough relative cost of matching a line
+    fn cost(&self) -> usize {
+        match self {
+            Filter::Raw(r) => r.cost(),
+            Filter::Multi(m) => match m.matcher {
+                Matcher::Literals(_) => 3,
+                Matcher::Regexes(_) => 12,
+            },
+            Filter::Or(rs) | Filter::And(rs) => rs.iter().map(Filter::cost).sum(),
+            Filter::Not(r) => r.cost(),
+            Filter::Named(_, r) => r.cost(),
+            Filter::Field(_, r) => 2 + r.cost(),
+        }
+    }
+}
+
+impl RawFilter {
+    fn cost(&self) -> usize {
+        // folding the case copies the line
+        let fold = self.ignore_case as usize;
+        fold + match self.filter {
+            Mode::Is(_) | Mode::Starts(_) | Mode::Ends(_) => 1,
+            Mode::Length(_) | Mode::Number(_) => 2,
+            Mode::Includes(_) => 3,
+            Mode::Subsequence(_) => 4,
+            Mode::Glob(_) => 6,
+            Mode::Regex(_) => 8,
+            Mode::Fuzzy(..) => 10,
+        }
+    }
+
+    // the key filters are merged on, case sensitivity and whether it's a literal
+    fn mergeable(&self, invert: bool) -> Option<(bool, bool)> {
+        if self.invert != invert {
+            return None;
+        }
+        match &self.filter {
+            // only ASCII folds the same as lowercasing the line
+            Mode::Includes(rf) if !rf.is_empty() && (!self.ignore_case || rf.is_ascii()) => {
+                Some((true, self.ignore_case))
+            }
+            // the case is part of the pattern
+            Mode::Regex(_) => Some((false, false)),
+            _ => None,
+        }
+    }
+}
+
+// `invert` is which raw filters can be merged, ones that match in an `or` and
+// inverted ones in an `and`, which match when none of the patterns do
+fn group(rs: Vec<Filter>, invert: bool, cnv: fn(Vec<Filter>) -> Filter) -> Filter {
+    let mut out = vec![];
+    let mut groups: Vec<((bool, bool), Vec<RawFilter>)> = vec![];
+    for r in rs.into_iter().map(Filter::compile) {
+        let Filter::Raw(raw) = r else {
+            out.push(r);
+            continue;
+        };
+        let Some(key) = raw.mergeable(invert) else {
+            out.push(Filter::Raw(raw));
+            continue;
+        };
+        match groups.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, filters)) => filters.push(raw),
+            None => groups.push((key, vec![raw])),
+        }
+    }
+    for ((literal, ignore_case), mut filters) in groups {
+        if filters.len() == 1 {
+            out.extend(filters.pop().map(Filter::Raw));
+            continue;
+        }
+        match multi(filters, literal, ignore_case, invert) {
+            Ok(m) => out.push(Filter::Multi(m)),
+            // too big for an automaton, they stay as they were
+            Err(filters) => out.extend(filters.into_iter().map(Filter::Raw)),
+        }
+    }
+    out.sort_by_key(Filter::cost);
+    match out.len() {
+        1 => out.remove(0),
+        _ => cnv(out),
+    }
+}
+
+fn multi(
+    filters: Vec<RawFilter>,
+    literal: bool,
+    ignore_case: bool,
+    invert: bool,
+) -> Result<Multi, Vec<RawFilter>> {
+    let matcher = match literal {
+        true => {
+            let patterns = filters.iter().filter_map(|f| match &f.filter {
+                Mode::Includes(rf) => Some(rf),
+                _ => None,
+            });
+            let patterns: Vec<_> = patterns.map(|p| regex::escape(p)).collect();
+            RegexBuilder::new(&patterns.join("|"))
+                .case_insensitive(ignore_case)
+                .build()
+                .map(Matcher::Literals)
+                .ok()
+        }
+        false => {
+            let patterns = filters.iter().filter_map(|f| match &f.filter {
+                Mode::Regex(rf) => Some(rf.as_str()),
+                _ => None,
+            });
+            // case insensitive regexes were built with the flag, not in the pattern
+            let patterns = patterns.zip(&filters).map(|(p, f)| match f.ignore_case {
+                true => format!("(?i:{p})"),
+                false => p.to_string(),
+            });
+            RegexSet::new(patterns).map(Matcher::Regexes).ok()
+        }
+    };
+    match matcher {
+        Some(matcher) => Ok(Multi {
+            matcher,
+            invert,
+            filters,
+        }),
+        None => Err(filters),
+    }
+}
+
+impl Multi {
+    pub fn compare(&self, text: &str) -> bool {
+        self.invert
+            != match &self.matcher {
+                Matcher::Literals(re) => re.is_match(text),
+                Matcher::Regexes(set) => set.is_match(text),
+            }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compiled(src: &str) -> (Filter, Filter) {
+        (
+            expr::parse(src).unwrap(),
+            expr::parse(src).unwrap().compile(),
+        )
+    }
+
+    #[test]
+    fn merges_siblings() {
+        let (plain, f) = compiled("+foo | +bar | .\\d{3} | .^x | =baz");
+        let Filter::Or(rs) = &f else {
+            panic!("expected an or, got {f:?}")
+        };
+        // =baz is cheapest and goes first
+        assert!(matches!(rs[0], Filter::Raw(_)));
+        assert!(rs[1..].iter().all(|r| matches!(r, Filter::Multi(_))));
+        for line in ["a foo", "bar", "123", "xy", "baz", "nope", "12"] {
+            assert_eq!(f.compare(line), plain.compare(line), "{line}");
+        }
+
+        let (plain, f) = compiled("-foo & ic+BAR & i+baz & $x");
+        assert!(matches!(&f, Filter::And(rs) if rs.len() == 3));
+        for line in ["x", "foo x", "Bar x", "baz x", "y"] {
+            assert_eq!(f.compare(line), plain.compare(line), "{line}");
+        }
+    }
+
+    #[test]
+    fn case_insensitive() {
+        let (plain, f) = compiled("c+foo | c+bar | c.^x | .^y");
+        assert!(matches!(&f, Filter::Or(rs) if rs.len() == 2));
+        for line in ["FOO", "bAr", "Xz", "Yz", "yz", "other"] {
+            assert_eq!(f.compare(line), plain.compare(line), "{line}");
+        }
+    }
+}
@@ -0,0 +1,278 @@
+use crate::*;
+
+// single string expressions, `($.rs | $.toml) & !^target/`
+//
+// expr  := and ('|' and)*
+// and   := unary ('&' unary)*
+// unary := '!' unary | '(' expr ')' | column unary | '@' name | [i][c](mode)(pattern)
+// column := 'col(' (N | name) ')' | 'f' N
+//
+// a pattern is quoted ("..." or '...') or runs up to whitespace or one of
+// `&|()`, which can be escaped with `\`
+
+pub fn parse(src: &str) -> Result<Filter, FilteError> {
+    parse_with(src, &Definitions::default())
+}
+
+// `@name` filters are looked up in `defs`
+pub fn parse_with(src: &str, defs: &Definitions) -> Result<Filter, FilteError> {
+    let mut parser = Parser {
+        chars: src.chars().collect(),
+        pos: 0,
+        defs,
+    };
+    let filter = parser.or()?;
+    parser.skip_space();
+    match parser.peek() {
+        Some(c) => Err(parser.error(FilteError::Unexpected(c))),
+        None => Ok(filter),
+    }
+}
+
+struct Parser<'a> {
+    chars: Vec<char>,
+    pos: usize,
+    defs: &'a Definitions,
+}
+
+fn ends_pattern(c: char) -> bool {
+    c.is_whitespace() || "&|()".contains(c)
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn rest(&self) -> String {
+        self.chars[self.pos..].iter().collect()
+    }
+
+    fn skip_space(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    // columns are 1-indexed, in chars
+    fn error(&self, source: FilteError) -> FilteError {
+        self.error_at(self.pos, source)
+    }
+
+    fn error_at(&self, pos: usize, source: FilteError) -> FilteError {
+        FilteError::AtColumn {
+            col: pos + 1,
+            error: Box::new(source),
+        }
+    }
+
+    fn or(&mut self) -> Result<Filter, FilteError> {
+        self.joined('|', Self::and, Filter::Or)
+    }
+
+    fn and(&mut self) -> Result<Filter, FilteError> {
+        self.joined('&', Self::unary, Filter::And)
+    }
+
+    // one or more `next` separated by `op`, a single one isn't wrapped
+    fn joined(
+        &mut self,
+        op: char,
+        next: fn(&mut Self) -> Result<Filter, FilteError>,
+        cnv: fn(Vec<Filter>) -> Filter,
+    ) -> Result<Filter, FilteError> {
+        let mut out = vec![next(self)?];
+        loop {
+            self.skip_space();
+            if self.peek() != Some(op) {
+                break;
+            }
+            self.pos += 1;
+            out.push(next(self)?);
+        }
+        Ok(match out.len() {
+            1 => out.remove(0),
+            _ => cnv(out),
+        })
+    }
+
+    fn unary(&mut self) -> Result<Filter, FilteError> {
+        self.skip_space();
+        match self.peek() {
+            None => Err(self.error(FilteError::MissingCommand)),
+            Some('!') => {
+                self.pos += 1;
+                Ok(Filter::Not(Box::new(self.unary()?)))
+            }
+            Some('(') => {
+                let open = self.pos;
+                self.pos += 1;
+                let inner = self.or()?;
+                self.skip_space();
+                match self.peek() {
+                    Some(')') => {
+                        self.pos += 1;
+                        Ok(inner)
+                    }
+                    Some(c) => Err(self.error(FilteError::Unexpected(c))),
+                    None => Err(self.error_at(open, FilteError::MissingClose)),
+                }
+            }
+            Some(c) if "&|)".contains(c) => Err(self.error(FilteError::Unexpected(c))),
+            Some('@') => {
+                let start = self.pos;
+                self.pos += 1;
+                let name: String = self.chars[self.pos..]
+                    .iter()
+                    .take_while(|c| !ends_pattern(**c))
+                    .collect();
+                self.pos += name.chars().count();
+                let filter = self.defs.get(&name).map_err(|e| self.error_at(start, e))?;
+                Ok(Filter::Named(name, filter))
+            }
+            Some(_) => {
+                let rest = self.rest();
+                match fields::column_prefix(&rest).map_err(|e| self.error(e))? {
+                    Some((col, after)) => {
+                        self.pos += rest.chars().count() - after.chars().count();
+                        Ok(Filter::Field(col, Box::new(self.unary()?)))
+                    }
+                    None => self.raw(),
+                }
+            }
+        }
+    }
+
+    fn raw(&mut self) -> Result<Filter, FilteError> {
+        let start = self.pos;
+        let mut tx = String::new();
+        for flag in ['i', 'c'] {
+            if self.peek() == Some(flag) {
+                tx.push(flag);
+                self.pos += 1;
+            }
+        }
+        let mode = self.peek().filter(|c| !ends_pattern(*c));
+        let mode = mode.ok_or_else(|| self.error(FilteError::MissingMode))?;
+        tx.push(mode);
+        self.pos += 1;
+        tx += &self.pattern()?;
+        RawFilter::try_from(tx)
+            .map(Filter::Raw)
+            .map_err(|e| self.error_at(start, e))
+    }
+
+    fn pattern(&mut self) -> Result<String, FilteError> {
+        let mut out = String::new();
+        if let Some(quote @ ('"' | '\'')) = self.peek() {
+            let open = self.pos;
+            self.pos += 1;
+            loop {
+                match self.peek() {
+                    None => return Err(self.error_at(open, FilteError::UnclosedQuote)),
+                    Some(c) if c == quote => break,
+                    Some('\\') if matches!(self.chars.get(self.pos + 1), Some(&c) if c == quote || c == '\\') => {
+                        self.pos += 1;
+                        out.push(self.chars[self.pos]);
+                    }
+                    Some(c) => out.push(c),
+                }
+                self.pos += 1;
+            }
+            self.pos += 1;
+            return Ok(out);
+        }
+        while let Some(c) = self.peek().filter(|c| !ends_pattern(*c)) {
+            match (c, self.chars.get(self.pos + 1)) {
+                // other escapes are left for the regex or glob
+                ('\\', Some(&next)) if ends_pattern(next) || next == '\\' => {
+                    out.push(next);
+                    self.pos += 2;
+                }
+                _ => {
+                    out.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fields::Split;
+
+    fn column(src: &str) -> usize {
+        match parse(src) {
+            Err(FilteError::AtColumn { col, .. }) => col,
+            other => panic!("expected an error with a column, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn precedence_and_grouping() {
+        let f = parse("($.rs | $.toml) & !^target/").unwrap();
+        assert!(f.compare("src/main.rs"));
+        assert!(f.compare("Cargo.toml"));
+        assert!(!f.compare("target/debug/main.rs"));
+        assert!(!f.compare("README.md"));
+
+        // & binds tighter than |
+        let f = parse("=a | ^b & $c").unwrap();
+        assert!(f.compare("a"));
+        assert!(f.compare("bxc"));
+        assert!(!f.compare("bx"));
+    }
+
+    #[test]
+    fn numbers_lengths_and_case() {
+        let f = parse("f3>1000 & f1~..10").unwrap();
+        let fields = |line| f.compare_record(&Record::split(line, Split::Whitespace, &[]));
+        assert!(fields("7 x 2048"));
+        assert!(!fields("11 x 2048"));
+        assert!(!fields("7 x 999"));
+        assert!(!fields("7 x big"));
+        assert!(!fields("7 x inf"));
+        assert!(!fields("NaN x 2048"));
+        for pattern in [">inf", "<NaN", "~-infinity..0", "l>inf"] {
+            assert!(matches!(parse(pattern), Err(FilteError::AtColumn { .. })), "{pattern}");
+        }
+
+        let f = parse("l>3 & l<6 & ic=\"NO NO\"").unwrap();
+        assert!(f.compare("abcd"));
+        assert!(!f.compare("no no"));
+        assert!(parse("c.^ab$").unwrap().compare("AB"));
+        assert!(parse("c?*.RS").unwrap().compare("main.rs"));
+
+        assert_eq!(column("=a | >x"), 6);
+    }
+
+    #[test]
+    fn columns() {
+        let f = parse("col(2)(=ok | =fine) & !col(name)^x").unwrap();
+        assert_eq!(f.columns(), [&Column::Index(2), &Column::Named("name".into())]);
+        assert_eq!(column("=a | col(x"), 6);
+    }
+
+    #[test]
+    fn quoted_and_escaped_patterns() {
+        let f = parse(r#"."a|b" & i+"x y""#).unwrap();
+        assert!(f.compare("a"));
+        assert!(!f.compare("a x y"));
+        let f = parse(r"+\(\) & .\d").unwrap();
+        assert!(f.compare("f() 1"));
+        assert!(!f.compare("f() x"));
+    }
+
+    #[test]
+    fn errors_point_at_columns() {
+        assert_eq!(column("$.rs |"), 7);
+        assert_eq!(column("($.rs"), 1);
+        assert_eq!(column("$.rs )"), 6);
+        assert_eq!(column("$.rs & #x"), 8);
+        assert_eq!(column("$.rs & .a[b"), 8);
+        assert_eq!(column("+'abc"), 2);
+    }
+}
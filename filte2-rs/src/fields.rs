@@ -0,0 +1,244 @@
+use std::borrow::Cow;
+use std::ops::Range;
+
+use crate::FilteError;
+
+// how lines are split into fields
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Split {
+    // runs of whitespace, leading and trailing whitespace ignored (like awk)
+    #[default]
+    Whitespace,
+    Delimiter(char),
+    // delimited, fields may be quoted with "" and quotes escaped by doubling them
+    Csv(char),
+}
+
+// a field selected by col(...), (de)serialized as the number or name
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+pub enum Column {
+    // 1-indexed, 0 is the whole line
+    Index(usize),
+    // looked up in the header, the first line
+    Named(String),
+}
+
+// a line with its fields
+pub struct Record<'a> {
+    pub line: &'a str,
+    fields: Vec<Cow<'a, str>>,
+    // where each field is written in the line, inside the quotes for quoted CSV fields
+    ranges: Vec<Range<usize>>,
+    header: &'a [String],
+}
+
+impl Split {
+    pub fn split<'a>(&self, line: &'a str) -> Vec<Cow<'a, str>> {
+        match *self {
+            Split::Whitespace => line.split_whitespace().map(Cow::Borrowed).collect(),
+            Split::Delimiter(d) => line.split(d).map(Cow::Borrowed).collect(),
+            Split::Csv(d) => csv_fields(line, d).into_iter().map(|(f, _)| f).collect(),
+        }
+    }
+
+    // the fields and where they are in the line
+    fn split_ranges<'a>(&self, line: &'a str) -> (Vec<Cow<'a, str>>, Vec<Range<usize>>) {
+        match *self {
+            Split::Csv(d) => csv_fields(line, d).into_iter().unzip(),
+            _ => {
+                let fields = self.split(line);
+                let ranges = fields.iter().map(|f| range_in(line, f)).collect();
+                (fields, ranges)
+            }
+        }
+    }
+}
+
+// `part` is a slice of `line`
+fn range_in(line: &str, part: &str) -> Range<usize> {
+    let start = part.as_ptr() as usize - line.as_ptr() as usize;
+    start..start + part.len()
+}
+
+// a quoted field can't span lines here, the quote is closed at the end of the line
+fn csv_fields(line: &str, delimiter: char) -> Vec<(Cow<'_, str>, Range<usize>)> {
+    let mut out = vec![];
+    let mut rest = line;
+    loop {
+        let Some(quoted) = rest.strip_prefix('"') else {
+            match rest.split_once(delimiter) {
+                Some((field, next)) => {
+                    out.push((Cow::Borrowed(field), range_in(line, field)));
+                    rest = next;
+                    continue;
+                }
+                None => {
+                    out.push((Cow::Borrowed(rest), range_in(line, rest)));
+                    return out;
+                }
+            }
+        };
+        let mut field = String::new();
+        let mut chars = quoted.char_indices().peekable();
+        let mut end = quoted.len();
+        let mut closed = false;
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' if chars.peek().is_some_and(|(_, c)| *c == '"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => {
+                    end = i + 1;
+                    closed = true;
+                    break;
+                }
+                c => field.push(c),
+            }
+        }
+        let inside = &quoted[..end - closed as usize];
+        out.push((Cow::Owned(field), range_in(line, inside)));
+        // anything between the closing quote and the delimiter is dropped
+        match quoted[end..].split_once(delimiter) {
+            Some((_, next)) => rest = next,
+            None => return out,
+        }
+    }
+}
+
+impl<'a> Record<'a> {
+    // only the line, every column but 0 is missing
+    pub fn line(line: &'a str) -> Self {
+        Record {
+            line,
+            fields: vec![],
+            ranges: vec![],
+            header: &[],
+        }
+    }
+
+    pub fn split(line: &'a str, split: Split, header: &'a [String]) -> Self {
+        let (fields, ranges) = split.split_ranges(line);
+        Record {
+            line,
+            fields,
+            ranges,
+            header,
+        }
+    }
+
+    pub fn get(&self, column: &Column) -> Option<&str> {
+        let index = match column {
+            Column::Index(0) => return Some(self.line),
+            Column::Index(n) => n - 1,
+            Column::Named(name) => self.header.iter().position(|h| h == name)?,
+        };
+        self.fields.get(index).map(|f| f.as_ref())
+    }
+
+    // a field as it's written in the line and where it starts, quoted CSV
+    // fields are the text inside the quotes with "" escapes left in
+    pub fn raw(&self, column: &Column) -> Option<(usize, &'a str)> {
+        let range = match column {
+            Column::Index(0) => return Some((0, self.line)),
+            Column::Index(n) => self.ranges.get(n - 1)?,
+            Column::Named(name) => self.ranges.get(self.header.iter().position(|h| h == name)?)?,
+        };
+        Some((range.start, &self.line[range.clone()]))
+    }
+}
+
+// an offset in a quoted CSV field as an offset in the field as it's written,
+// where each '"' is escaped as ""
+pub fn written_offset(written: &str, offset: usize) -> usize {
+    let (mut field_at, mut written_at) = (0, 0);
+    let mut chars = written.chars().peekable();
+    while field_at < offset {
+        let Some(c) = chars.next() else { break };
+        if c == '"' && chars.next_if_eq(&'"').is_some() {
+            written_at += 1;
+        }
+        field_at += c.len_utf8();
+        written_at += c.len_utf8();
+    }
+    written_at
+}
+
+// `col(name)` or `fN` at the start of a filter, and the rest of it
+pub fn column_prefix(tx: &str) -> Result<Option<(Column, &str)>, FilteError> {
+    if let Some(rest) = tx.strip_prefix('f') {
+        let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        if digits > 0 {
+            let (n, rest) = rest.split_at(digits);
+            let n = n.parse().map_err(|_| FilteError::InvalidColumn(format!("f{n}")))?;
+            return Ok(Some((Column::Index(n), rest)));
+        }
+    }
+    let Some(rest) = tx.strip_prefix("col(") else {
+        return Ok(None);
+    };
+    let (name, rest) = rest
+        .split_once(')')
+        .ok_or(FilteError::InvalidColumn(tx.to_string()))?;
+    let column = name
+        .parse()
+        .map_err(|_| FilteError::InvalidColumn(format!("col({name})")))?;
+    Ok(Some((column, rest)))
+}
+
+impl std::str::FromStr for Column {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse() {
+            Ok(n) => Ok(Column::Index(n)),
+            Err(_) if s.is_empty() => Err(()),
+            Err(_) => Ok(Column::Named(s.to_string())),
+        }
+    }
+}
+
+impl std::fmt::Display for Column {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Column::Index(n) => write!(f, "col({n})"),
+            Column::Named(name) => write!(f, "col({name})"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits() {
+        assert_eq!(Split::Whitespace.split("  a  b\tc "), ["a", "b", "c"]);
+        assert_eq!(Split::Delimiter(':').split("a::b"), ["a", "", "b"]);
+        assert_eq!(
+            Split::Csv(',').split(r#"a,"b,c","say ""hi""",,"open"#),
+            ["a", "b,c", r#"say "hi""#, "", "open"]
+        );
+    }
+
+    #[test]
+    fn columns() {
+        let header = ["pid".to_string(), "status".to_string()];
+        let rec = Record::split("12,ok", Split::Csv(','), &header);
+        assert_eq!(rec.get(&"status".parse().unwrap()), Some("ok"));
+        assert_eq!(rec.get(&Column::Index(1)), Some("12"));
+        assert_eq!(rec.get(&Column::Index(0)), Some("12,ok"));
+        assert_eq!(rec.get(&Column::Index(3)), None);
+        assert_eq!(rec.get(&"user".parse().unwrap()), None);
+
+        let rec = Record::split(r#"a,"b,c","say ""hi""",x"#, Split::Csv(','), &[]);
+        assert_eq!(rec.raw(&Column::Index(1)), Some((0, "a")));
+        assert_eq!(rec.raw(&Column::Index(2)), Some((3, "b,c")));
+        assert_eq!(rec.raw(&Column::Index(3)), Some((9, r#"say ""hi"""#)));
+        assert_eq!(rec.raw(&Column::Index(4)), Some((21, "x")));
+        let rec = Record::split(r#"1,"open"#, Split::Csv(','), &[]);
+        assert_eq!(rec.raw(&Column::Index(2)), Some((3, "open")));
+        let rec = Record::split("a  b", Split::Whitespace, &[]);
+        assert_eq!(rec.raw(&Column::Index(2)), Some((3, "b")));
+    }
+}
@@ -0,0 +1,191 @@
+use crate::*;
+
+// the edit distance lev.c prints, to the closest word of the text. A pattern
+// of several words is compared to as many words in a row, joined by a space
+pub fn distance(pattern: &[char], text: &str) -> usize {
+    let n = pattern
+        .split(|c| c.is_whitespace())
+        .filter(|w| !w.is_empty())
+        .count()
+        .max(1);
+    let words: Vec<&str> = text.split_whitespace().collect();
+    if words.len() < n {
+        return levenshtein(pattern, &words.join(" "));
+    }
+    words
+        .windows(n)
+        .map(|w| levenshtein(pattern, &w.join(" ")))
+        .min()
+        .unwrap_or(pattern.len())
+}
+
+// Wagner-Fischer, one row at a time
+fn levenshtein(pattern: &[char], text: &str) -> usize {
+    // row[j], the distance of pattern[..j] to the text so far
+    let mut row: Vec<usize> = (0..=pattern.len()).collect();
+    for (i, c) in text.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for j in 1..=pattern.len() {
+            let sub = diagonal + (pattern[j - 1] != c) as usize;
+            diagonal = row[j];
+            row[j] = sub.min(row[j] + 1).min(row[j - 1] + 1);
+        }
+    }
+    row[pattern.len()]
+}
+
+// the pattern's chars in order, like fzf: the shortest window ending at the
+// first place the whole pattern fits, as byte offsets of each matched char
+pub fn subsequence(pattern: &[char], text: &str) -> Option<Vec<usize>> {
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let mut p = 0;
+    let mut end = None;
+    for (i, &(_, c)) in chars.iter().enumerate() {
+        if p < pattern.len() && pattern[p] == c {
+            p += 1;
+            if p == pattern.len() {
+                end = Some(i);
+                break;
+            }
+        }
+    }
+    let end = match (pattern.is_empty(), end) {
+        (true, _) => return Some(vec![]),
+        (false, end) => end?,
+    };
+    // back from the end for the latest start, then forward again for the positions
+    let mut p = pattern.len();
+    let mut start = end;
+    for i in (0..=end).rev() {
+        if pattern[p - 1] == chars[i].1 {
+            p -= 1;
+            if p == 0 {
+                start = i;
+                break;
+            }
+        }
+    }
+    let mut out = Vec::with_capacity(pattern.len());
+    let mut p = 0;
+    for &(offset, c) in &chars[start..=end] {
+        if p < pattern.len() && pattern[p] == c {
+            out.push(offset);
+            p += 1;
+        }
+    }
+    Some(out)
+}
+
+// higher is better: every matched char counts, more so following another
+// match or starting a word, and gaps cost
+pub fn subsequence_score(positions: &[usize], text: &str) -> i64 {
+    let mut score = 0;
+    let mut last: Option<usize> = None;
+    for &at in positions {
+        score += 16;
+        let prev = text[..at].chars().next_back();
+        let here = text[at..].chars().next();
+        let boundary = match (prev, here) {
+            (None, _) => true,
+            (Some(p), Some(h)) => !p.is_alphanumeric() || (p.is_lowercase() && h.is_uppercase()),
+            _ => false,
+        };
+        if boundary {
+            score += 8;
+        }
+        match last {
+            Some(l) if text[l..at].chars().count() == 1 => score += 8,
+            Some(l) => score -= 3 + text[l..at].chars().count() as i64 - 2,
+            None => (),
+        }
+        last = Some(at);
+    }
+    score
+}
+
+impl Filter {
+    // how well a matching record matches, higher is better, only fuzzy filters
+    // score anything: the subsequence score or minus the edit distance
+    pub fn score(&self, rec: &Record) -> i64 {
+        match self {
+            Filter::Raw(r) => r.score(rec.line),
+            Filter::And(rs) => rs.iter().map(|r| r.score(rec)).sum(),
+            Filter::Or(rs) => rs
+                .iter()
+                .filter(|r| r.compare_record(rec))
+                .map(|r| r.score(rec))
+                .max()
+                .unwrap_or(0),
+            Filter::Not(_) | Filter::Multi(_) => 0,
+            Filter::Field(col, r) => rec.get(col).map_or(0, |f| r.score(&Record::line(f))),
+            Filter::Named(_, r) => r.score(rec),
+        }
+    }
+}
+
+impl RawFilter {
+    fn score(&self, text: &str) -> i64 {
+        if self.invert {
+            return 0;
+        }
+        let folded = match self.ignore_case {
+            true => text.to_lowercase(),
+            false => text.to_string(),
+        };
+        match &self.filter {
+            Mode::Fuzzy(rf, _) => -(distance(rf, &folded) as i64),
+            Mode::Subsequence(rf) => subsequence(rf, &folded)
+                .map_or(0, |positions| subsequence_score(&positions, &folded)),
+            _ => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chars(s: &str) -> Vec<char> {
+        s.chars().collect()
+    }
+
+    #[test]
+    fn distances() {
+        assert_eq!(distance(&chars("color"), "the colour red"), 1);
+        assert_eq!(distance(&chars("123"), "122"), 1);
+        // same as lev.c
+        assert_eq!(distance(&chars("kitten"), "sitting"), 3);
+        assert_eq!(distance(&chars("flaw"), "lawn"), 2);
+        assert_eq!(distance(&chars("abc"), ""), 3);
+        // words, not any part of the line
+        assert_eq!(distance(&chars("cat"), "concatenate"), 8);
+        assert_eq!(distance(&chars("red car"), "a red  cab"), 1);
+        assert_eq!(distance(&chars("red car"), "red"), 4);
+    }
+
+    #[test]
+    fn subsequences() {
+        assert_eq!(
+            subsequence(&chars("fzm"), "fuzzy match"),
+            Some(vec![0, 2, 6])
+        );
+        // the shortest window, not the first f
+        assert_eq!(subsequence(&chars("fb"), "f x fb"), Some(vec![4, 5]));
+        assert_eq!(subsequence(&chars("ba"), "ab"), None);
+
+        let score = |p: &str, t: &str| subsequence_score(&subsequence(&chars(p), t).unwrap(), t);
+        assert!(score("main", "src/main.rs") > score("main", "src/my_animation.rs"));
+        assert!(score("fm", "foo_main") > score("fm", "foomain"));
+
+        let f = expr::parse("*fzm | %1:colour").unwrap();
+        let rank = |line| f.score(&Record::line(line));
+        assert!(rank("fuzzy match") > rank("for the zoom"));
+        assert!(rank("the colour") > rank("the color"));
+
+        for empty in ["%2:", "%", "%'1: '"] {
+            let error = expr::parse(empty).unwrap_err();
+            assert!(matches!(error, FilteError::AtColumn { error, .. } if matches!(*error, FilteError::MissingText)));
+        }
+    }
+}
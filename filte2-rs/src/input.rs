@@ -0,0 +1,111 @@
+use std::borrow::Cow;
+use std::io::{self, BufRead};
+use std::path::PathBuf;
+
+// records split on newlines (a "\r\n" counts as one) or on NULs, read as bytes
+// so input that isn't valid UTF-8 doesn't stop the run
+pub struct Records<R: BufRead> {
+    reader: R,
+    separator: u8,
+    buf: Vec<u8>,
+}
+
+impl<R: BufRead> Records<R> {
+    pub fn new(reader: R, null: bool) -> Self {
+        Records {
+            reader,
+            separator: if null { b'\0' } else { b'\n' },
+            buf: vec![],
+        }
+    }
+
+    // the next record without its separator, the buffer is reused between records
+    pub fn next_record(&mut self) -> io::Result<Option<&[u8]>> {
+        self.buf.clear();
+        if self.reader.read_until(self.separator, &mut self.buf)? == 0 {
+            return Ok(None);
+        }
+        let mut record = self.buf.as_slice();
+        if let Some(rest) = record.strip_suffix(&[self.separator]) {
+            record = rest;
+        }
+        if self.separator == b'\n' {
+            record = record.strip_suffix(b"\r").unwrap_or(record);
+        }
+        Ok(Some(record))
+    }
+}
+
+// the record as text to match on, invalid bytes become U+FFFD and only match
+// patterns that contain it
+pub fn text(record: &[u8]) -> Cow<'_, str> {
+    // checking first is faster for the usual, valid, records
+    match std::str::from_utf8(record) {
+        Ok(text) => Cow::Borrowed(text),
+        Err(_) => String::from_utf8_lossy(record),
+    }
+}
+
+// the record as a path, on unix paths are bytes and are kept as they were read
+pub fn path(record: &[u8]) -> PathBuf {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        PathBuf::from(std::ffi::OsStr::from_bytes(record))
+    }
+    #[cfg(not(unix))]
+    PathBuf::from(text(record).into_owned())
+}
+
+// an offset in `text(record)` as an offset in `record`, the bytes a U+FFFD
+// replaced start where it does
+pub fn raw_offset(record: &[u8], offset: usize) -> usize {
+    let (mut text_at, mut raw_at) = (0, 0);
+    for chunk in record.utf8_chunks() {
+        let valid = chunk.valid().len();
+        if offset <= text_at + valid {
+            return raw_at + offset - text_at;
+        }
+        text_at += valid;
+        raw_at += valid;
+        if !chunk.invalid().is_empty() {
+            if offset < text_at + '\u{fffd}'.len_utf8() {
+                return raw_at;
+            }
+            text_at += '\u{fffd}'.len_utf8();
+            raw_at += chunk.invalid().len();
+        }
+    }
+    raw_at
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn records(input: &[u8], null: bool) -> Vec<Vec<u8>> {
+        let mut records = Records::new(input, null);
+        let mut out = vec![];
+        while let Some(r) = records.next_record().unwrap() {
+            out.push(r.to_vec());
+        }
+        out
+    }
+
+    #[test]
+    fn separators() {
+        assert_eq!(records(b"a\r\nb\n\nc", false), [&b"a"[..], b"b", b"", b"c"]);
+        assert_eq!(records(b"a\nb\0c\0", true), [&b"a\nb"[..], b"c"]);
+        assert_eq!(text(b"ok \xff"), "ok \u{fffd}");
+    }
+
+    #[test]
+    fn raw_offsets() {
+        let record = b"a\xff\xfeb\xe2\x82c";
+        let text = text(record);
+        assert_eq!(text, "a\u{fffd}\u{fffd}b\u{fffd}c");
+        let raw: Vec<_> = text.char_indices().map(|(i, _)| raw_offset(record, i)).collect();
+        assert_eq!(raw, [0, 1, 2, 3, 4, 6]);
+        assert_eq!(raw_offset(record, text.len()), record.len());
+    }
+}
@@ -22,9 +22,39 @@ pub enum FilteError {
 
     #[error("Missing mode")]
     MissingMode,
+
+    #[error("Unexpected {0:?}")]
+    Unexpected(char),
+    #[error("Unclosed quote")]
+    UnclosedQuote,
+    #[error("{error} at column {col}")]
+    AtColumn { col: usize, error: Box<FilteError> },
+    #[error("Argument {0} needs a value")]
+    MissingArgValue(String),
+    #[error("Invalid value {1} for argument {0}")]
+    InvalidArgValue(String, String),
+    #[error("-d takes a single character, not {0:?} (put -- before an exclude filter starting with -d)")]
+    InvalidDelimiter(String),
+    #[error("Invalid column {0}, expected col(N), fN or col(name)")]
+    InvalidColumn(String),
+    #[error("Invalid number {0}")]
+    InvalidNumber(String),
+    #[error("No filter named @{0}")]
+    UnknownFilter(String),
+    #[error("Filter @{0} references itself")]
+    RecursiveFilter(String),
+    #[error("{error} in @{name}")]
+    InFilter {
+        name: String,
+        error: Box<FilteError>,
+    },
+    #[error("Invalid definition on line {0}, expected name = expression")]
+    InvalidDefinition(usize),
+    #[error("Can't read filters from {0}: {1}")]
+    FiltersFile(std::path::PathBuf, std::io::Error),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Mode {
     Is(String),
     Starts(String),
@@ -32,45 +62,182 @@ pub enum Mode {
     Includes(String),
     Regex(regex::Regex),
     Glob(glob::Pattern),
+    // the text as a number, text that isn't one never matches
+    Number(Cmp),
+    // the length of the text in chars
+    Length(Cmp),
+    // a word of the text within this many edits of the pattern
+    Fuzzy(Vec<char>, usize),
+    // the pattern's chars in order, with anything between them
+    Subsequence(Vec<char>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Cmp {
+    Equal(f64),
+    Greater(f64),
+    Less(f64),
+    // inclusive
+    Range(f64, f64),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct RawFilter {
     filter: Mode,
     invert: bool,
+    // string patterns are lowercased when the filter is built
+    ignore_case: bool,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Filter {
     Raw(RawFilter),
     And(Vec<Filter>),
     Or(Vec<Filter>),
     Not(Box<Filter>),
+    // the filter applied to a single field
+    Field(Column, Box<Filter>),
+    // `@name`, shared by every reference to it
+    Named(String, Arc<Filter>),
+    // raw filters merged by `compile`
+    Multi(Multi),
 }
 
 impl Filter {
     pub fn compare(&self, text: &str) -> bool {
+        self.compare_record(&Record::line(text))
+    }
+
+    pub fn compare_record(&self, rec: &Record) -> bool {
+        match self {
+            Filter::Raw(r) => r.compare(rec.line),
+            Filter::Or(rs) => rs.iter().any(|r| r.compare_record(rec)),
+            Filter::And(rs) => rs.iter().all(|r| r.compare_record(rec)),
+            Filter::Not(r) => !r.compare_record(rec),
+            // missing fields never match
+            Filter::Field(col, r) => rec.get(col).is_some_and(|f| r.compare(f)),
+            Filter::Named(_, r) => r.compare_record(rec),
+            Filter::Multi(m) => m.compare(rec.line),
+        }
+    }
+
+    // the columns selected anywhere in the filter
+    pub fn columns(&self) -> Vec<&Column> {
         match self {
-            Filter::Raw(r) => r.compare(text),
-            Filter::Or(rs) => rs.iter().any(|r| r.compare(text)),
-            Filter::And(rs) => rs.iter().all(|r| r.compare(text)),
-            Filter::Not(r) => !r.compare(text),
+            Filter::Raw(_) | Filter::Multi(_) => vec![],
+            Filter::Or(rs) | Filter::And(rs) => rs.iter().flat_map(Filter::columns).collect(),
+            Filter::Not(r) => r.columns(),
+            Filter::Field(col, r) => std::iter::once(col).chain(r.columns()).collect(),
+            Filter::Named(_, r) => r.columns(),
         }
     }
 }
 
 impl RawFilter {
     pub fn compare(&self, text: &str) -> bool {
+        let lower;
+        let folded = match self.ignore_case {
+            true => {
+                lower = text.to_lowercase();
+                lower.as_str()
+            }
+            false => text,
+        };
         self.invert
             != match &self.filter {
-                Mode::Is(rf) => rf == text,
-                Mode::Starts(rf) => text.starts_with(rf),
-                Mode::Ends(rf) => text.ends_with(rf),
-                Mode::Includes(rf) => text.contains(rf),
+                Mode::Is(rf) => rf == folded,
+                Mode::Starts(rf) => folded.starts_with(rf),
+                Mode::Ends(rf) => folded.ends_with(rf),
+                Mode::Includes(rf) => folded.contains(rf),
+                // built case insensitive
                 Mode::Regex(rf) => rf.is_match(text),
-                Mode::Glob(rf) => rf.matches(text),
+                Mode::Glob(rf) => rf.matches_with(
+                    text,
+                    glob::MatchOptions {
+                        case_sensitive: !self.ignore_case,
+                        ..Default::default()
+                    },
+                ),
+                Mode::Number(cmp) => number(text).is_some_and(|n| cmp.matches(n)),
+                Mode::Length(cmp) => cmp.matches(text.chars().count() as f64),
+                Mode::Fuzzy(rf, max) => fuzzy::distance(rf, folded) <= *max,
+                Mode::Subsequence(rf) => fuzzy::subsequence(rf, folded).is_some(),
             }
     }
 }
 
+// text as a number, "inf" and "NaN" aren't ones
+pub fn number(text: &str) -> Option<f64> {
+    text.trim().parse().ok().filter(|n: &f64| n.is_finite())
+}
+
+impl Cmp {
+    pub fn matches(&self, n: f64) -> bool {
+        match *self {
+            Cmp::Equal(x) => n == x,
+            Cmp::Greater(x) => n > x,
+            Cmp::Less(x) => n < x,
+            Cmp::Range(lo, hi) => lo <= n && n <= hi,
+        }
+    }
+}
+
+// as written after `l`, open ends of ranges are left out
+impl std::fmt::Display for Cmp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let end = |x: f64| match x.is_infinite() {
+            true => String::new(),
+            false => x.to_string(),
+        };
+        match *self {
+            Cmp::Equal(x) => write!(f, "={x}"),
+            Cmp::Greater(x) => write!(f, ">{x}"),
+            Cmp::Less(x) => write!(f, "<{x}"),
+            Cmp::Range(lo, hi) => write!(f, "~{}..{}", end(lo), end(hi)),
+        }
+    }
+}
+
+// the filter as it would be parsed, `ic.^ab`
+impl std::fmt::Display for RawFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.invert {
+            write!(f, "i")?;
+        }
+        if self.ignore_case {
+            write!(f, "c")?;
+        }
+        match &self.filter {
+            Mode::Is(rf) => write!(f, "={rf}"),
+            Mode::Starts(rf) => write!(f, "^{rf}"),
+            Mode::Ends(rf) => write!(f, "${rf}"),
+            Mode::Includes(rf) => write!(f, "+{rf}"),
+            Mode::Regex(rf) => write!(f, ".{}", rf.as_str()),
+            Mode::Glob(rf) => write!(f, "?{}", rf.as_str()),
+            // there's no number equal mode
+            Mode::Number(Cmp::Equal(x)) => write!(f, "{}", Cmp::Range(*x, *x)),
+            Mode::Number(cmp) => write!(f, "{cmp}"),
+            Mode::Length(cmp) => write!(f, "l{cmp}"),
+            Mode::Fuzzy(rf, max) => write!(f, "%{max}:{}", rf.iter().collect::<String>()),
+            Mode::Subsequence(rf) => write!(f, "*{}", rf.iter().collect::<String>()),
+        }
+    }
+}
+
+pub mod args;
+pub mod builder;
+pub mod compile;
+pub mod expr;
+pub mod fields;
+pub mod fuzzy;
+pub mod input;
+pub mod named;
+pub mod output;
 pub mod reader;
+pub mod search;
+pub mod tree;
+
+use compile::Multi;
+use fields::{Column, Record};
+use named::Definitions;
+use std::sync::Arc;
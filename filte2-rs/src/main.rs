@@ -1,11 +1,20 @@
+use filte2_rs::input::Records;
+use filte2_rs::named::Definitions;
+use filte2_rs::output::Printer;
+use filte2_rs::search::Search;
 use filte2_rs::*;
-use std::io::{self, Write, BufRead};
+use std::io::{self, BufWriter, IsTerminal, Write};
+use std::path::PathBuf;
 
-const HELP: &'static str = "
-usage filter [i](mode)(pattern)
+const HELP: &str = "
+usage filter [options] [i](mode)(pattern) [FILE...]
+      filter [options] -e EXPRESSION [FILE...]
 
 excludes lines that don't fit the patterns with speficied modes
 
+reads stdin, or the files after the filter (- is stdin) and under -r directories,
+each on its own thread, printing matches as path:line:text
+
 if 'i' is the prefix of a mode the filter will exclude lines the *do* fit the pattern
 
 modes:
@@ -16,9 +25,53 @@ $|z : ends with $pattern
 -|e : excludes $pattern (shorthand for i+)
 .|r : matches regex $pattern
 ?|g : matches glob $pattern
+>   : number greater than $pattern
+<   : number less than $pattern
+~   : number in range $pattern, lo..hi (inclusive, either end can be left out)
+l   : length, followed by >N, <N, ~lo..hi or N
+%   : a word within N edits of $pattern (Levenshtein, like lev), N:pattern (just pattern is 1 edit)
+      a pattern of several words is compared to as many words in a row
+*   : has the chars of $pattern in order, with anything between them, like fzf
+
+if 'c' comes before a mode (after the 'i') the pattern is case insensitive
 
 filters can be grouped with and[ ... ], or[ ... ] or not[ . ] to execute multiple filters at once with logical joinings in them
 
+-e|--expr takes all filters as a single expression, with & (and), | (or), ! (not) and parenthesis
+    filter -e '($.rs | $.toml) & !^target/'
+patterns end at whitespace or one of &|(), quote them (\"...\" or '...') or escape those with \\
+
+col(N), fN or col(name) before a filter applies it to a single field (1-indexed, 0 is the whole line)
+named columns are looked up in the first line, which is left out unless --header is given
+    filter -d, --header 'col(status)=ok'
+    filter 'f5>1000'
+
+options, before the filters (-- ends them):
+-d|--delimiter C : split fields on C instead of whitespace, also -dC
+--csv            : split fields as CSV (quoted fields, \"\" escapes), on -d or ','
+--header         : print the first line as it is, without filtering it
+--highlight      : colour the matched parts of each line
+-o|--only-matching : print only the matched parts, each on its own line
+--replace T      : replace the matched parts with T, $0 is the match, regex groups are $1, ${name}...
+-A N | -B N | -C N : print N lines of context after, before or around each match
+-c|--count       : print the number of matching lines instead
+--rank           : print matches best first, by how closely % and * filters match (per file, no context)
+-0|--null        : records are separated by NUL instead of newlines, in and out (find -print0, xargs -0)
+--line-buffered  : flush every line, even when not writing to a terminal
+--explain        : print the filter as a tree, as it will run, instead of reading input
+-r|--recursive DIR : read every file under DIR, can be given more than once
+--paths EXPR     : only read the files whose paths match the -e expression EXPR,
+                   without a filter the paths are printed instead, without files
+                   they're read from stdin (one per line, or per NUL with -0)
+--filters FILE   : read @name filters from FILE instead of ~/.config/filte/filters
+
+an exclude filter that looks like an option (-dz, -o, -c...) has to come after --,
+or use the e mode instead: filter -- -dz, filter edz
+
+@name uses a filter defined in ~/.config/filte/filters, one per line as name = expression
+    source-files = $.rs | $.toml
+    rust = @source-files & !^target/
+
 the only way to print this text is to execute with no arguments
 ";
 
@@ -28,27 +81,97 @@ fn help() -> ! {
 }
 
 fn main() -> eyre::Result<()> {
-    let mut stdout = io::stdout().lock();
     let args: Vec<_> = std::env::args().skip(1).collect();
-    if args.len() == 0 {
+    if args.is_empty() {
         help();
     }
-    let filters = reader::parse(args.into_iter())?;
+    let args = args::Args::parse(args)?;
+    // the default file is optional, one given with --filters isn't
+    let defs = match (&args.definitions, Definitions::default_path()) {
+        (Some(path), _) => Definitions::load(path)?,
+        (None, Some(path)) if path.exists() => Definitions::load(&path)?,
+        _ => Definitions::default(),
+    };
+    let mut words = args.filters.into_iter();
+    // with --paths and no filter the paths are only listed
+    let filters = match (&args.expr, &args.paths, words.len()) {
+        (Some(expr), _, _) => Some(expression(expr, &defs)?),
+        (None, Some(_), 0) => None,
+        (None, _, _) => Some(reader::parse_with(words.by_ref(), &defs)?),
+    };
+    let paths = args.paths.as_deref().map(|p| expression(p, &defs)).transpose()?;
+    // named filters are only shared with the definitions until they're dropped
+    drop(defs);
+    let filters = filters.map(Filter::compile);
+    let paths = paths.map(Filter::compile);
+    if args.explain {
+        match (&paths, &filters) {
+            (Some(paths), Some(filters)) => print!("paths:\n{}contents:\n{}", paths.explain(), filters.explain()),
+            (Some(f), None) | (None, Some(f)) => print!("{}", f.explain()),
+            (None, None) => (),
+        }
+        return Ok(());
+    }
+    let mut files = args.files;
+    files.extend(words.map(PathBuf::from));
+    files.extend(args.recursive.iter().flat_map(|dir| search::walk(dir)));
+    let reads_files = !files.is_empty() || !args.recursive.is_empty();
+    // --paths without files filters the paths given on stdin
+    if let (Some(_), false) = (&paths, reads_files) {
+        files = search::read_paths(&mut Records::new(io::stdin().lock(), args.output.null))?;
+    }
 
-    for line in io::stdin().lock().lines() {
-        let line = line?;
-        if filters.compare(&line) {
-            writeln!(stdout, "{}", line).inspect_err(die_on_pipe)?;
+    // stdout only flushes every line on its own
+    let stdout = io::stdout();
+    let mut stdout: Box<dyn Write + Send> = match args.line_buffered || stdout.is_terminal() {
+        true => Box::new(stdout),
+        false => Box::new(BufWriter::new(stdout)),
+    };
+    let search = |filter| Search {
+        filter,
+        split: args.split,
+        header: args.header,
+        output: args.output.clone(),
+    };
+    if let (Some(paths), None) = (&paths, &filters) {
+        let search = search(paths);
+        let mut out = Printer::new(stdout, search.output.clone());
+        search.paths(&files, &mut out).inspect_err(die_on_pipe)?;
+        out.finish().inspect_err(die_on_pipe)?;
+        return Ok(());
+    }
+    let search = search(filters.as_ref().expect("parsed unless --paths lists paths"));
+    if let Some(paths) = &paths {
+        files = search::filter_paths(paths, args.split, files);
+    }
+    if reads_files || paths.is_some() {
+        let failed = search.files(&files, &mut stdout).inspect_err(die_on_pipe)?;
+        stdout.flush().inspect_err(die_on_pipe)?;
+        if failed > 0 {
+            std::process::exit(2);
         }
+    } else {
+        let mut records = Records::new(io::stdin().lock(), search.output.null);
+        let mut out = Printer::new(stdout, search.output.clone());
+        search.records(&mut records, &mut out).inspect_err(die_on_pipe)?;
+        out.finish().inspect_err(die_on_pipe)?;
     }
     Ok(())
 }
 
+// point at the column under the expression
+fn expression(expr: &str, defs: &Definitions) -> Result<Filter, FilteError> {
+    expr::parse_with(expr, defs).inspect_err(|e| {
+        if let FilteError::AtColumn { col, .. } = e {
+            eprintln!("{expr}\n{:>col$}", "^");
+        }
+    })
+}
+
 // in case piped to head, don't eprint on closed pipe
 fn die_on_pipe(r: &io::Error)  {
-    match r.kind() {
-        io::ErrorKind::BrokenPipe=>std::process::exit(0),
-        _=>(),
-    };
+    if r.kind() == io::ErrorKind::BrokenPipe {
+        std::process::exit(0)
+    }
 }
 
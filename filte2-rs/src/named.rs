@@ -0,0 +1,151 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::*;
+
+// named filters, referenced as `@name` on the command line, in expressions and
+// in other definitions
+//
+// one definition per line, `name = expression`, lines starting with whitespace
+// continue the one before, `#` starts a comment line
+//
+//     source-files = $.rs | $.toml
+//     no-build = !^target/ & !+/node_modules/
+//     rust = @source-files & @no-build
+//
+// definitions are only parsed when referenced, each one once
+#[derive(Debug, Default)]
+pub struct Definitions {
+    source: HashMap<String, String>,
+    parsed: RefCell<HashMap<String, Arc<Filter>>>,
+    // the definitions being parsed, to catch ones that reference themselves
+    resolving: RefCell<Vec<String>>,
+}
+
+impl Definitions {
+    // `$XDG_CONFIG_HOME/filte/filters` or `~/.config/filte/filters`
+    pub fn default_path() -> Option<PathBuf> {
+        let config = match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+        };
+        Some(config.join("filte").join("filters"))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, FilteError> {
+        let src = std::fs::read_to_string(path)
+            .map_err(|e| FilteError::FiltersFile(path.to_path_buf(), e))?;
+        Self::parse(&src)
+    }
+
+    pub fn parse(src: &str) -> Result<Self, FilteError> {
+        let mut out = Self::default();
+        let mut last: Option<String> = None;
+        for (n, line) in src.lines().enumerate() {
+            if line.trim().is_empty() || line.trim_start().starts_with('#') {
+                continue;
+            }
+            if let (true, Some(name)) = (line.starts_with(char::is_whitespace), &last) {
+                let expr = out
+                    .source
+                    .get_mut(name)
+                    .expect("defined on an earlier line");
+                *expr += " ";
+                *expr += line.trim();
+                continue;
+            }
+            let (name, expr) = line
+                .split_once('=')
+                .map(|(name, expr)| (name.trim(), expr.trim()))
+                .filter(|(name, _)| is_name(name))
+                .ok_or(FilteError::InvalidDefinition(n + 1))?;
+            out.source.insert(name.to_string(), expr.to_string());
+            last = Some(name.to_string());
+        }
+        Ok(out)
+    }
+
+    // the filter named `name`, parsed the first time it's asked for
+    pub fn get(&self, name: &str) -> Result<Arc<Filter>, FilteError> {
+        if let Some(filter) = self.parsed.borrow().get(name) {
+            return Ok(filter.clone());
+        }
+        if self.resolving.borrow().iter().any(|n| n == name) {
+            return Err(FilteError::RecursiveFilter(name.to_string()));
+        }
+        let src = self
+            .source
+            .get(name)
+            .ok_or(FilteError::UnknownFilter(name.to_string()))?;
+        self.resolving.borrow_mut().push(name.to_string());
+        let filter = expr::parse_with(src, self);
+        self.resolving.borrow_mut().pop();
+        let filter = Arc::new(filter.map_err(|e| FilteError::InFilter {
+            name: name.to_string(),
+            error: Box::new(e),
+        })?);
+        self.parsed
+            .borrow_mut()
+            .insert(name.to_string(), filter.clone());
+        Ok(filter)
+    }
+}
+
+pub fn is_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SRC: &str = "
+# comment
+source-files = $.rs | $.toml
+no-build = !^target/ &
+    !+/node_modules/
+rust = @source-files & @no-build
+loop = =a | @loop2
+loop2 = @loop
+";
+
+    #[test]
+    fn nested_definitions() {
+        let defs = Definitions::parse(SRC).unwrap();
+        let rust = defs.get("rust").unwrap();
+        assert!(rust.compare("src/main.rs"));
+        assert!(!rust.compare("target/main.rs"));
+        assert!(!rust.compare("a/node_modules/x.toml"));
+        // parsed once, shared by every reference
+        assert!(Arc::ptr_eq(&rust, &defs.get("rust").unwrap()));
+
+        let f = reader::parse_with(
+            ["or[", "@rust", "=x", "]"].map(String::from).into_iter(),
+            &defs,
+        );
+        assert!(f.unwrap().compare("x"));
+    }
+
+    #[test]
+    fn errors() {
+        let defs = Definitions::parse(SRC).unwrap();
+        assert!(matches!(
+            defs.get("nope"),
+            Err(FilteError::UnknownFilter(_))
+        ));
+        let err = defs.get("loop").unwrap_err().to_string();
+        assert_eq!(
+            err,
+            "Filter @loop references itself at column 1 in @loop2 at column 6 in @loop"
+        );
+        assert!(matches!(
+            Definitions::parse("a = =x\nb c"),
+            Err(FilteError::InvalidDefinition(2))
+        ));
+    }
+}
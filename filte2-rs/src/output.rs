@@ -0,0 +1,512 @@
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::io::{self, Write};
+
+use regex::Regex;
+
+use crate::fields::{self, Record};
+use crate::{fuzzy, input, Filter, Mode, RawFilter};
+
+const HIGHLIGHT: &str = "\x1b[1;31m";
+const RESET: &str = "\x1b[0m";
+
+// what is printed for a matching line
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Transform {
+    #[default]
+    Line,
+    // the matched parts in colour
+    Highlight,
+    // each matched part on its own line
+    OnlyMatching,
+    // matched parts replaced, `$0` is the match and regex groups are `$1`, `${name}`...
+    Replace(String),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct OutputOptions {
+    pub transform: Transform,
+    // lines of context before and after each match
+    pub before: usize,
+    pub after: usize,
+    // only print how many lines matched
+    pub count: bool,
+    // records end with NUL instead of a newline, in and out
+    pub null: bool,
+    // matches sorted by their score once the input is read, without context
+    pub rank: bool,
+}
+
+// a matched part of a line, in bytes
+#[derive(Debug, Clone)]
+pub struct Span<'a> {
+    pub start: usize,
+    pub end: usize,
+    // regex matches keep the regex, the text it ran on and where the match
+    // starts in it to expand captures
+    regex: Option<(Cow<'a, Regex>, Cow<'a, str>, usize)>,
+}
+
+impl Filter {
+    // the parts of a matching line that made it match, inverted filters have none
+    pub fn spans<'a>(&'a self, rec: &Record<'a>) -> Vec<Span<'a>> {
+        match self {
+            Filter::Raw(r) => r.spans(rec.line),
+            Filter::Or(rs) | Filter::And(rs) => rs
+                .iter()
+                .filter(|r| r.compare_record(rec))
+                .flat_map(|r| r.spans(rec))
+                .collect(),
+            Filter::Not(_) => vec![],
+            Filter::Named(_, r) => r.spans(rec),
+            Filter::Multi(m) => m
+                .filters
+                .iter()
+                .filter(|r| r.compare(rec.line))
+                .flat_map(|r| r.spans(rec.line))
+                .collect(),
+            Filter::Field(col, r) => {
+                let (Some((offset, written)), Some(field)) = (rec.raw(col), rec.get(col)) else {
+                    return vec![];
+                };
+                if written == field {
+                    return r
+                        .spans(&Record::line(written))
+                        .into_iter()
+                        .map(|s| s.shifted(offset))
+                        .collect();
+                }
+                // a quoted CSV field with "" escapes, found in the field and
+                // mapped back to where it's written
+                let at = |i| offset + fields::written_offset(written, i);
+                r.spans(&Record::line(field))
+                    .into_iter()
+                    .map(|s| Span {
+                        start: at(s.start),
+                        end: at(s.end),
+                        ..s.into_owned()
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+impl RawFilter {
+    fn spans<'a>(&'a self, text: &'a str) -> Vec<Span<'a>> {
+        if self.invert {
+            return vec![];
+        }
+        let folded = match self.ignore_case {
+            true => text.to_lowercase(),
+            false => text.to_string(),
+        };
+        // lowercasing changed some byte lengths, the offsets would be off
+        if folded.len() != text.len() {
+            return vec![Span::new(0, text.len())];
+        }
+        match &self.filter {
+            Mode::Starts(rf) => vec![Span::new(0, rf.len())],
+            Mode::Ends(rf) => vec![Span::new(text.len() - rf.len(), text.len())],
+            Mode::Includes(rf) if !rf.is_empty() => folded
+                .match_indices(rf.as_str())
+                .map(|(i, m)| Span::new(i, i + m.len()))
+                .collect(),
+            Mode::Regex(re) => re
+                .find_iter(text)
+                .filter(|m| !m.is_empty())
+                .map(|m| Span {
+                    start: m.start(),
+                    end: m.end(),
+                    regex: Some((Cow::Borrowed(re), Cow::Borrowed(text), m.start())),
+                })
+                .collect(),
+            // each matched char, like fzf
+            Mode::Subsequence(rf) => fuzzy::subsequence(rf, &folded)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|at| {
+                    let len = folded[at..].chars().next().map_or(0, char::len_utf8);
+                    Span::new(at, at + len)
+                })
+                .collect(),
+            _ => vec![Span::new(0, text.len())],
+        }
+    }
+}
+
+impl<'a> Span<'a> {
+    fn new(start: usize, end: usize) -> Self {
+        Span {
+            start,
+            end,
+            regex: None,
+        }
+    }
+
+    fn shifted(self, offset: usize) -> Self {
+        Span {
+            start: self.start + offset,
+            end: self.end + offset,
+            regex: self.regex,
+        }
+    }
+
+    // for spans found in text that doesn't live as long as the line
+    fn into_owned(self) -> Span<'static> {
+        Span {
+            start: self.start,
+            end: self.end,
+            regex: self.regex.map(|(re, text, at)| {
+                (Cow::Owned(re.into_owned()), Cow::Owned(text.into_owned()), at)
+            }),
+        }
+    }
+
+    pub fn replacement(&self, line: &str, template: &str) -> String {
+        let matched = &line[self.start..self.end];
+        match &self.regex {
+            Some((re, text, at)) => {
+                let mut out = String::new();
+                if let Some(caps) = re.captures_at(text, *at) {
+                    caps.expand(template, &mut out);
+                }
+                out
+            }
+            None => template.replace("${0}", matched).replace("$0", matched),
+        }
+    }
+}
+
+// sorted, overlapping spans are dropped (highlight merges them, touching ones too)
+fn ordered(mut spans: Vec<Span>, merge: bool) -> Vec<Span> {
+    spans.sort_by_key(|s| (s.start, std::cmp::Reverse(s.end)));
+    let mut out: Vec<Span> = vec![];
+    for span in spans {
+        match out.last_mut() {
+            Some(last) if span.start < last.end || (merge && span.start == last.end) => {
+                if merge {
+                    last.end = last.end.max(span.end);
+                }
+            }
+            _ => out.push(span),
+        }
+    }
+    out
+}
+
+// prints matching lines, their context and separators between groups like grep
+pub struct Printer<W: Write> {
+    out: W,
+    opts: OutputOptions,
+    // context lines that may be printed before the next match
+    before: VecDeque<(usize, Vec<u8>)>,
+    after_left: usize,
+    last_printed: Option<usize>,
+    pub matched: usize,
+    // reading files, lines are prefixed with `path:line:` (`path-line-` for context)
+    path: Option<String>,
+    // matches as they'll be printed and their score, with --rank
+    ranked: Vec<(i64, Vec<u8>)>,
+}
+
+impl<W: Write> Printer<W> {
+    pub fn new(out: W, opts: OutputOptions) -> Self {
+        Printer {
+            out,
+            before: VecDeque::with_capacity(opts.before),
+            opts,
+            after_left: 0,
+            last_printed: None,
+            matched: 0,
+            path: None,
+            ranked: vec![],
+        }
+    }
+
+    pub fn with_path(mut self, path: String) -> Self {
+        self.path = Some(path);
+        self
+    }
+
+    // plain lines and counts don't need to know what matched
+    pub fn needs_spans(&self) -> bool {
+        !self.opts.count && self.opts.transform != Transform::Line
+    }
+
+    // lines printed as they are, like a CSV header
+    pub fn raw(&mut self, n: usize, record: &[u8]) -> io::Result<()> {
+        if self.opts.count {
+            return Ok(());
+        }
+        self.prefix(n, ':')?;
+        self.record(record)
+    }
+
+    // `spans` is None for records that didn't match, they're offsets in `input::text(record)`
+    pub fn line(&mut self, n: usize, record: &[u8], spans: Option<Vec<Span>>) -> io::Result<()> {
+        let Some(spans) = spans else {
+            return self.context(n, record);
+        };
+        self.matched += 1;
+        if self.opts.count {
+            return Ok(());
+        }
+        while let Some((bn, before)) = self.before.pop_front() {
+            self.separate(bn)?;
+            self.prefix(bn, '-')?;
+            self.record(&before)?;
+        }
+        self.separate(n)?;
+        self.after_left = self.opts.after;
+        let line = input::text(record);
+        // spans are in the text, invalid UTF-8 is printed as it was read
+        let raw = |at: usize| match line {
+            Cow::Borrowed(_) => at,
+            Cow::Owned(_) => input::raw_offset(record, at),
+        };
+        if self.opts.transform != Transform::OnlyMatching {
+            self.prefix(n, ':')?;
+        }
+        match &self.opts.transform {
+            Transform::Line => self.record(record),
+            Transform::Highlight => {
+                let mut at = 0;
+                for span in ordered(spans, true) {
+                    let (start, end) = (raw(span.start), raw(span.end));
+                    self.out.write_all(&record[at..start])?;
+                    self.out.write_all(HIGHLIGHT.as_bytes())?;
+                    self.out.write_all(&record[start..end])?;
+                    self.out.write_all(RESET.as_bytes())?;
+                    at = end;
+                }
+                self.record(&record[at..])
+            }
+            Transform::OnlyMatching => {
+                for span in ordered(spans, false) {
+                    self.prefix(n, ':')?;
+                    self.record(&record[raw(span.start)..raw(span.end)])?;
+                }
+                Ok(())
+            }
+            Transform::Replace(template) => {
+                let mut at = 0;
+                for span in ordered(spans, false) {
+                    self.out.write_all(&record[at..raw(span.start)])?;
+                    write!(self.out, "{}", span.replacement(&line, template))?;
+                    at = raw(span.end);
+                }
+                self.record(&record[at..])
+            }
+        }
+    }
+
+    // a match kept until `finish` to be printed in order of score, highest first
+    pub fn ranked(&mut self, n: usize, record: &[u8], spans: Vec<Span>, score: i64) -> io::Result<()> {
+        self.matched += 1;
+        if self.opts.count {
+            return Ok(());
+        }
+        let opts = OutputOptions {
+            before: 0,
+            after: 0,
+            ..self.opts.clone()
+        };
+        let mut line = Printer::new(vec![], opts);
+        line.path = self.path.clone();
+        line.line(n, record, Some(spans))?;
+        self.ranked.push((score, line.out));
+        Ok(())
+    }
+
+    fn context(&mut self, n: usize, record: &[u8]) -> io::Result<()> {
+        if self.opts.count {
+            return Ok(());
+        }
+        if self.after_left > 0 {
+            self.after_left -= 1;
+            self.separate(n)?;
+            self.prefix(n, '-')?;
+            return self.record(record);
+        }
+        if self.opts.before > 0 {
+            if self.before.len() == self.opts.before {
+                self.before.pop_front();
+            }
+            self.before.push_back((n, record.to_vec()));
+        }
+        Ok(())
+    }
+
+    // `--` between groups of lines that aren't next to each other, only with context
+    fn separate(&mut self, n: usize) -> io::Result<()> {
+        let context = self.opts.before > 0 || self.opts.after > 0;
+        if let (true, Some(last)) = (context, self.last_printed) {
+            if n > last + 1 {
+                self.record(b"--")?;
+            }
+        }
+        self.last_printed = Some(n);
+        Ok(())
+    }
+
+    fn prefix(&mut self, n: usize, separator: char) -> io::Result<()> {
+        match &self.path {
+            Some(path) => write!(self.out, "{path}{separator}{n}{separator}"),
+            None => Ok(()),
+        }
+    }
+
+    // a record and the newline or NUL after it
+    fn record(&mut self, record: &[u8]) -> io::Result<()> {
+        self.out.write_all(record)?;
+        self.out.write_all(if self.opts.null { b"\0" } else { b"\n" })
+    }
+
+    // the output, flushed
+    pub fn finish(mut self) -> io::Result<W> {
+        // stable, equal scores stay in the order they were read
+        self.ranked.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        for (_, line) in &self.ranked {
+            self.out.write_all(line)?;
+        }
+        match (&self.path, self.opts.count) {
+            (Some(path), true) => writeln!(self.out, "{path}:{}", self.matched)?,
+            (None, true) => writeln!(self.out, "{}", self.matched)?,
+            (_, false) => (),
+        }
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::parse;
+
+    fn print(filter: &str, opts: OutputOptions, input: &str) -> String {
+        let filter = parse(filter).unwrap();
+        let mut printer = Printer::new(vec![], opts);
+        for (n, line) in input.lines().enumerate() {
+            let rec = Record::line(line);
+            let spans = filter.compare_record(&rec).then(|| filter.spans(&rec));
+            printer.line(n, line.as_bytes(), spans).unwrap();
+        }
+        String::from_utf8(printer.finish().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn transforms() {
+        let only = OutputOptions {
+            transform: Transform::OnlyMatching,
+            ..Default::default()
+        };
+        assert_eq!(print(r".\d+", only, "a1 b22\nc"), "1\n22\n");
+
+        let replace = |t: &str| OutputOptions {
+            transform: Transform::Replace(t.to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            print(r#"."(?<k>\w+)=(\w+)""#, replace("$2:${k}"), "a=1, b=2"),
+            "1:a, 2:b\n"
+        );
+        assert_eq!(print("+b & ^a", replace("[$0]"), "abcb"), "[a][b]c[b]\n");
+
+        let highlight = OutputOptions {
+            transform: Transform::Highlight,
+            ..Default::default()
+        };
+        assert_eq!(
+            print("c+AB", highlight, "xaby"),
+            format!("x{HIGHLIGHT}ab{RESET}y\n")
+        );
+    }
+
+    #[test]
+    fn invalid_utf8_is_printed_as_read() {
+        let filter = parse("+b").unwrap();
+        let record = b"\xffab\xfeb";
+        let text = input::text(record);
+        let spans = filter.spans(&Record::line(&text));
+        let print = |transform| {
+            let opts = OutputOptions {
+                transform,
+                ..Default::default()
+            };
+            let mut printer = Printer::new(vec![], opts);
+            printer.line(1, record, Some(spans.clone())).unwrap();
+            printer.finish().unwrap()
+        };
+        let (h, r) = (HIGHLIGHT.as_bytes(), RESET.as_bytes());
+        let highlighted = [&b"\xffa"[..], h, b"b", r, b"\xfe", h, b"b", r, b"\n"].concat();
+        assert_eq!(print(Transform::Highlight), highlighted);
+        assert_eq!(print(Transform::OnlyMatching), b"b\nb\n");
+        assert_eq!(print(Transform::Replace("[$0]".into())), b"\xffa[b]\xfe[b]\n");
+    }
+
+    #[test]
+    fn quoted_csv_fields() {
+        let print = |filter: &str, line: &str, transform| {
+            let filter = parse(filter).unwrap();
+            let rec = Record::split(line, crate::fields::Split::Csv(','), &[]);
+            let opts = OutputOptions {
+                transform,
+                ..Default::default()
+            };
+            let mut printer = Printer::new(vec![], opts);
+            printer.line(1, line.as_bytes(), Some(filter.spans(&rec))).unwrap();
+            String::from_utf8(printer.finish().unwrap()).unwrap()
+        };
+        let replace = |t: &str| Transform::Replace(t.into());
+        assert_eq!(
+            print("col(2)+b & f3.h.", r#"b,"a,b","say ""hi""""#, replace("[$0]")),
+            r#"b,"a,[b]","say ""[hi]""""#.to_string() + "\n"
+        );
+
+        // found in the field without its "" escapes, printed where it's written
+        let escaped = r#""""é""#;
+        assert_eq!(print(r#"f1^\"é"#, escaped, replace("[$0]")), "\"[\"\"é]\"\n");
+        assert_eq!(
+            print(r#"f1^\"é"#, escaped, Transform::Highlight),
+            format!("\"{HIGHLIGHT}\"\"é{RESET}\"\n")
+        );
+        assert_eq!(print(r#"f2$\"b"#, r#"x,"é""b""#, replace("[$0]")), "x,\"é[\"\"b]\"\n");
+        assert_eq!(print(r#"f1.^a\"b$"#, r#""a""b""#, Transform::OnlyMatching), "a\"\"b\n");
+        assert_eq!(print(r#"f1.a\"\(b\)"#, r#""a""b",x"#, replace("<$1>")), "\"<b>\",x\n");
+    }
+
+    #[test]
+    fn context() {
+        let opts = OutputOptions {
+            before: 1,
+            after: 1,
+            ..Default::default()
+        };
+        let input = "1\nx\n2\n3\n4\n5\nx\n6";
+        assert_eq!(print("=x", opts, input), "1\nx\n2\n--\n5\nx\n6\n");
+    }
+
+    #[test]
+    fn ranked() {
+        let filter = parse("*fzm").unwrap();
+        let opts = OutputOptions {
+            rank: true,
+            ..Default::default()
+        };
+        let mut printer = Printer::new(vec![], opts);
+        for (n, line) in ["for the zoom", "nope", "fuzzy match"].into_iter().enumerate() {
+            let rec = Record::line(line);
+            match filter.compare_record(&rec) {
+                true => printer.ranked(n, line.as_bytes(), vec![], filter.score(&rec)),
+                false => printer.line(n, line.as_bytes(), None),
+            }
+            .unwrap();
+        }
+        assert_eq!(
+            String::from_utf8(printer.finish().unwrap()).unwrap(),
+            "fuzzy match\nfor the zoom\n"
+        );
+    }
+}
@@ -10,8 +10,16 @@ enum CdFilter {
 }
 
 pub fn parse(args: impl Iterator<Item = String>) -> Result<Filter, FilteError> {
+    parse_with(args, &Definitions::default())
+}
+
+// `@name` filters are looked up in `defs`
+pub fn parse_with(
+    args: impl Iterator<Item = String>,
+    defs: &Definitions,
+) -> Result<Filter, FilteError> {
     let mut cmds = compile(args);
-    next(&mut cmds)
+    next(&mut cmds, defs)
 }
 
 fn compile(args: impl Iterator<Item = String>) -> impl Iterator<Item = CdFilter> {
@@ -24,28 +32,32 @@ fn compile(args: impl Iterator<Item = String>) -> impl Iterator<Item = CdFilter>
     })
 }
 
-fn next(args: &mut impl Iterator<Item = CdFilter>) -> Result<Filter, FilteError> {
+fn next(
+    args: &mut impl Iterator<Item = CdFilter>,
+    defs: &Definitions,
+) -> Result<Filter, FilteError> {
     let arg = args.next().ok_or(FilteError::MissingCommand)?;
     match arg {
-        CdFilter::Not => csin(args, Filter::Not),
-        CdFilter::And => cvec(args, Filter::And),
-        CdFilter::Or => cvec(args, Filter::Or),
+        CdFilter::Not => csin(args, Filter::Not, defs),
+        CdFilter::And => cvec(args, Filter::And, defs),
+        CdFilter::Or => cvec(args, Filter::Or, defs),
         CdFilter::Close => Err(FilteError::NeedlessClose),
-        CdFilter::Raw(s) => raw(s),
+        CdFilter::Raw(s) => raw(s, defs),
     }
 }
 
 fn csin(
     args: &mut impl Iterator<Item = CdFilter>,
     cnv: fn(Box<Filter>) -> Filter,
+    defs: &Definitions,
 ) -> Result<Filter, FilteError> {
     let arg = args.next().ok_or(FilteError::MissingClose)?;
     let out = match arg {
-        CdFilter::Not => csin(args, Filter::Not),
-        CdFilter::And => cvec(args, Filter::And),
-        CdFilter::Or => cvec(args, Filter::Or),
+        CdFilter::Not => csin(args, Filter::Not, defs),
+        CdFilter::And => cvec(args, Filter::And, defs),
+        CdFilter::Or => cvec(args, Filter::Or, defs),
         CdFilter::Close => Err(FilteError::MissingCommand)?,
-        CdFilter::Raw(r) => raw(r),
+        CdFilter::Raw(r) => raw(r, defs),
     }?;
     let closer = args.next().ok_or(FilteError::MissingClose)?;
     if let CdFilter::Close = closer {
@@ -58,22 +70,29 @@ fn csin(
 fn cvec(
     args: &mut impl Iterator<Item = CdFilter>,
     cnv: fn(Vec<Filter>) -> Filter,
+    defs: &Definitions,
 ) -> Result<Filter, FilteError> {
     let mut out = Vec::new();
     loop {
         let arg = args.next().ok_or(FilteError::MissingClose)?;
         out.push(match arg {
-            CdFilter::Not => csin(args, Filter::Not),
-            CdFilter::And => cvec(args, Filter::And),
-            CdFilter::Or => cvec(args, Filter::Or),
+            CdFilter::Not => csin(args, Filter::Not, defs),
+            CdFilter::And => cvec(args, Filter::And, defs),
+            CdFilter::Or => cvec(args, Filter::Or, defs),
             CdFilter::Close => break,
-            CdFilter::Raw(r) => raw(r),
+            CdFilter::Raw(r) => raw(r, defs),
         }?);
     }
     Ok(cnv(out))
 }
 
-fn raw(pattern: String) -> Result<Filter, FilteError> {
+fn raw(pattern: String, defs: &Definitions) -> Result<Filter, FilteError> {
+    if let Some((col, rest)) = fields::column_prefix(&pattern)? {
+        return Ok(Filter::Field(col, Box::new(raw(rest.to_string(), defs)?)));
+    }
+    if let Some(name) = pattern.strip_prefix('@') {
+        return Ok(Filter::Named(name.to_string(), defs.get(name)?));
+    }
     RawFilter::try_from(pattern).map(Filter::Raw)
 }
 
@@ -87,20 +106,91 @@ impl TryFrom<String> for RawFilter {
         if mode == 'i' {
             mode = chars.next().ok_or(FilteError::MissingMode)?;
         }
+        let ignore_case = mode == 'c';
+        if mode == 'c' {
+            mode = chars.next().ok_or(FilteError::MissingMode)?;
+        }
         let pattern: String = chars.collect();
+        let text = match ignore_case {
+            true => pattern.to_lowercase(),
+            false => pattern.clone(),
+        };
         let filter = match mode {
-            '=' => Is(pattern),
-            's' | '^' => Starts(pattern),
-            'z' | '$' => Ends(pattern),
-            'h' | '+' => Includes(pattern),
+            '=' => Is(text),
+            's' | '^' => Starts(text),
+            'z' | '$' => Ends(text),
+            'h' | '+' => Includes(text),
             'e' | '-' => {
                 invert = !invert;
-                Includes(pattern)
+                Includes(text)
             }
-            'r' | '.' => Regex(pattern.try_into()?),
+            'r' | '.' => Regex(
+                regex::RegexBuilder::new(&pattern)
+                    .case_insensitive(ignore_case)
+                    .build()?,
+            ),
             'g' | '?' => Glob(glob::Pattern::new(&pattern)?),
+            '>' => Number(Cmp::Greater(number(&pattern)?)),
+            '<' => Number(Cmp::Less(number(&pattern)?)),
+            '~' => Number(range(&pattern)?),
+            'l' => Length(length(&pattern)?),
+            '%' => {
+                let (max, word) = distance(&text)?;
+                Fuzzy(word.chars().collect(), max)
+            }
+            '*' => Subsequence(text.chars().collect()),
             other => Err(FilteError::NoFilter(other))?,
         };
-        Ok(RawFilter { filter, invert })
+        Ok(RawFilter {
+            filter,
+            invert,
+            ignore_case,
+        })
+    }
+}
+
+fn number(tx: &str) -> Result<f64, FilteError> {
+    crate::number(tx).ok_or(FilteError::InvalidNumber(tx.to_string()))
+}
+
+// `lo..hi`, either end may be left out
+fn range(tx: &str) -> Result<Cmp, FilteError> {
+    let (lo, hi) = tx
+        .split_once("..")
+        .ok_or(FilteError::InvalidNumber(tx.to_string()))?;
+    let bound = |b: &str, open: f64| match b {
+        "" => Ok(open),
+        b => number(b),
+    };
+    Ok(Cmp::Range(
+        bound(lo, f64::NEG_INFINITY)?,
+        bound(hi, f64::INFINITY)?,
+    ))
+}
+
+// `>N`, `<N`, `~lo..hi`, `=N` or just `N`
+fn length(tx: &str) -> Result<Cmp, FilteError> {
+    let mut chars = tx.chars();
+    match chars.next() {
+        Some('>') => Ok(Cmp::Greater(number(chars.as_str())?)),
+        Some('<') => Ok(Cmp::Less(number(chars.as_str())?)),
+        Some('~') => range(chars.as_str()),
+        Some('=') => Ok(Cmp::Equal(number(chars.as_str())?)),
+        _ => Ok(Cmp::Equal(number(tx)?)),
+    }
+}
+
+// `N:word` or just `word`, within one edit
+fn distance(tx: &str) -> Result<(usize, &str), FilteError> {
+    let (max, word) = match tx.split_once(':') {
+        Some((n, word)) if !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()) => n
+            .parse()
+            .map(|n| (n, word))
+            .map_err(|_| FilteError::InvalidNumber(n.to_string()))?,
+        _ => (1, tx),
+    };
+    match word.trim().is_empty() {
+        true => Err(FilteError::MissingText),
+        false => Ok((max, word)),
     }
 }
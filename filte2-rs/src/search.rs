@@ -0,0 +1,380 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use crate::fields::{Column, Record, Split};
+use crate::input::{self, Records};
+use crate::output::{OutputOptions, Printer, Span};
+use crate::Filter;
+
+// a filter and how to read and print what it matches, for stdin or files
+pub struct Search<'a> {
+    pub filter: &'a Filter,
+    pub split: Split,
+    // the first line is printed as it is instead of filtered
+    pub header: bool,
+    pub output: OutputOptions,
+}
+
+impl Search<'_> {
+    // every record of one input, lines are numbered from 1
+    pub fn records<R: BufRead, W: Write>(
+        &self,
+        records: &mut Records<R>,
+        out: &mut Printer<W>,
+    ) -> io::Result<()> {
+        let columns = self.filter.columns();
+        let split_fields = !columns.is_empty();
+        let named = columns.iter().any(|c| matches!(c, Column::Named(_)));
+        let mut n = 1;
+        // named columns are looked up in the first line, which isn't filtered
+        let mut header = vec![];
+        if named || self.header {
+            if let Some(record) = records.next_record()? {
+                let line = input::text(record);
+                header = self.split.split(&line).into_iter().map(|f| f.into_owned()).collect();
+                if self.header {
+                    out.raw(n, record)?;
+                }
+                n += 1;
+            }
+        }
+        while let Some(record) = records.next_record()? {
+            let line = input::text(record);
+            let rec = match split_fields {
+                true => Record::split(&line, self.split, &header),
+                false => Record::line(&line),
+            };
+            self.matches(n, record, &rec, out)?;
+            n += 1;
+        }
+        Ok(())
+    }
+
+    // printed now, or with --rank once everything's been read
+    fn matches<W: Write>(
+        &self,
+        n: usize,
+        record: &[u8],
+        rec: &Record,
+        out: &mut Printer<W>,
+    ) -> io::Result<()> {
+        match (self.spans(rec, out.needs_spans()), self.output.rank) {
+            (Some(spans), true) => out.ranked(n, record, spans, self.filter.score(rec)),
+            (spans, _) => out.line(n, record, spans),
+        }
+    }
+
+    // None if the record doesn't match, its spans are only found if they're printed
+    fn spans<'a>(&'a self, rec: &Record<'a>, needed: bool) -> Option<Vec<Span<'a>>> {
+        match (self.filter.compare_record(rec), needed) {
+            (false, _) => None,
+            (true, false) => Some(vec![]),
+            (true, true) => Some(self.filter.spans(rec)),
+        }
+    }
+
+    // each file is filtered on its own thread, up to one per core at a time,
+    // and written to `out` in the order given: the first file that isn't done
+    // is written as it's read, the ones after it are kept until it is. Files
+    // that can't be read are reported and counted
+    pub fn files<W: Write + Send>(&self, paths: &[PathBuf], out: &mut W) -> io::Result<usize> {
+        let next = AtomicUsize::new(0);
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let order = InOrder {
+            head: AtomicUsize::new(0),
+            state: Mutex::new(Written {
+                out,
+                done: BTreeMap::new(),
+                failed: 0,
+                error: None,
+            }),
+        };
+        std::thread::scope(|s| {
+            for _ in 0..threads.min(paths.len()) {
+                s.spawn(|| loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(path) = paths.get(i) else {
+                        break;
+                    };
+                    let result = self.file(path, Slot::new(&order, i));
+                    // nobody's listening after a write error
+                    if order.done(i, result, paths).is_err() {
+                        break;
+                    }
+                });
+            }
+        });
+        let state = order.state.into_inner().unwrap();
+        match state.error {
+            Some(e) => Err(e),
+            None => Ok(state.failed),
+        }
+    }
+
+    // everything printed for one file, `-` is stdin
+    fn file<'o, W: Write>(&self, path: &Path, out: Slot<'o, W>) -> io::Result<Slot<'o, W>> {
+        let mut out = Printer::new(out, self.output.clone()).with_path(path.display().to_string());
+        match path.to_str() {
+            Some("-") => self.records(&mut Records::new(io::stdin().lock(), self.output.null), &mut out)?,
+            _ => {
+                let file = BufReader::new(File::open(path)?);
+                self.records(&mut Records::new(file, self.output.null), &mut out)?
+            }
+        }
+        out.finish()
+    }
+
+    // the filter applied to the paths themselves instead of what's in the files
+    pub fn paths<W: Write>(&self, paths: &[PathBuf], out: &mut Printer<W>) -> io::Result<()> {
+        for (n, path) in paths.iter().enumerate() {
+            // matched as text, printed as it is
+            let text = path.to_string_lossy();
+            let rec = Record::split(&text, self.split, &[]);
+            self.matches(n + 1, path.as_os_str().as_encoded_bytes(), &rec, out)?;
+        }
+        Ok(())
+    }
+}
+
+// the paths `filter` matches, for --paths
+pub fn filter_paths(filter: &Filter, split: Split, paths: Vec<PathBuf>) -> Vec<PathBuf> {
+    paths
+        .into_iter()
+        .filter(|p| {
+            let path = p.to_string_lossy();
+            filter.compare_record(&Record::split(&path, split, &[]))
+        })
+        .collect()
+}
+
+// a path per record, empty ones are left out
+pub fn read_paths<R: BufRead>(records: &mut Records<R>) -> io::Result<Vec<PathBuf>> {
+    let mut paths = vec![];
+    while let Some(record) = records.next_record()? {
+        if !record.is_empty() {
+            paths.push(input::path(record));
+        }
+    }
+    Ok(paths)
+}
+
+// files written to `out` in order, see Search::files
+struct InOrder<W> {
+    // the file written as it's read
+    head: AtomicUsize,
+    state: Mutex<Written<W>>,
+}
+
+struct Written<W> {
+    out: W,
+    // files after the head that are done, what they printed or why they failed
+    done: BTreeMap<usize, io::Result<Vec<u8>>>,
+    failed: usize,
+    // writing to `out` failed, nothing more is read
+    error: Option<io::Error>,
+}
+
+impl<W: Write> InOrder<W> {
+    // the file is done, it and the done files after it are written once the
+    // ones before them are
+    fn done(&self, i: usize, result: io::Result<Slot<W>>, paths: &[PathBuf]) -> Result<(), ()> {
+        let mut state = self.state.lock().unwrap();
+        if state.error.is_some() {
+            return Err(());
+        }
+        state.done.insert(i, result.map(|slot| slot.buf));
+        let mut head = self.head.load(Ordering::Acquire);
+        while let Some(result) = state.done.remove(&head) {
+            let written = match result {
+                Ok(buf) => state.out.write_all(&buf),
+                Err(e) => state.out.flush().map(|_| {
+                    eprintln!("filte: {}: {e}", paths[head].display());
+                    state.failed += 1;
+                }),
+            };
+            if let Err(e) = written {
+                state.error = Some(e);
+                return Err(());
+            }
+            head += 1;
+            self.head.store(head, Ordering::Release);
+        }
+        Ok(())
+    }
+}
+
+// one file's output, written through while it's the head and kept until then
+struct Slot<'o, W> {
+    order: &'o InOrder<W>,
+    i: usize,
+    buf: Vec<u8>,
+}
+
+impl<'o, W: Write> Slot<'o, W> {
+    fn new(order: &'o InOrder<W>, i: usize) -> Self {
+        Slot {
+            order,
+            i,
+            buf: vec![],
+        }
+    }
+}
+
+impl<W: Write> Write for Slot<'_, W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(data);
+        self.flush()?;
+        Ok(data.len())
+    }
+
+    // only what's kept, `out` is flushed by whoever owns it
+    fn flush(&mut self) -> io::Result<()> {
+        if self.buf.is_empty() || self.order.head.load(Ordering::Acquire) != self.i {
+            return Ok(());
+        }
+        let mut state = self.order.state.lock().unwrap();
+        if state.error.is_some() {
+            return Err(io::ErrorKind::BrokenPipe.into());
+        }
+        let written = state.out.write_all(&self.buf);
+        self.buf.clear();
+        written.inspect_err(|e| state.error = Some(io::Error::new(e.kind(), e.to_string())))
+    }
+}
+
+// every file under `dir`, sorted, symlinks aren't followed and directories
+// that can't be read are reported and skipped
+pub fn walk(dir: &Path) -> Vec<PathBuf> {
+    let mut out = vec![];
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("filte: {}: {e}", dir.display());
+            return out;
+        }
+    };
+    let mut entries: Vec<_> = entries
+        .filter_map(|entry| {
+            entry
+                .inspect_err(|e| eprintln!("filte: {}: {e}", dir.display()))
+                .ok()
+        })
+        .collect();
+    entries.sort_by_key(|e| e.file_name());
+    for entry in entries {
+        match entry.file_type() {
+            Ok(t) if t.is_dir() => out.extend(walk(&entry.path())),
+            Ok(t) if t.is_file() => out.push(entry.path()),
+            Ok(_) => (),
+            Err(e) => eprintln!("filte: {}: {e}", entry.path().display()),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn files_in_order_with_prefixes() {
+        let dir = std::env::temp_dir().join(format!("filte-search-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("b.txt"), "x\nTODO b\n").unwrap();
+        std::fs::write(dir.join("sub/a.txt"), "TODO a\r\nno\n").unwrap();
+        std::fs::write(dir.join("c.txt"), "TODO c").unwrap();
+
+        let filter = crate::expr::parse("+TODO").unwrap();
+        let search = Search {
+            filter: &filter,
+            split: Split::Whitespace,
+            header: false,
+            output: OutputOptions::default(),
+        };
+        let files = walk(&dir);
+        let mut out = vec![];
+        assert_eq!(search.files(&files, &mut out).unwrap(), 0);
+        let d = dir.display();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!("{d}/b.txt:2:TODO b\n{d}/c.txt:1:TODO c\n{d}/sub/a.txt:1:TODO a\n")
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn path_prefilter() {
+        let mut records = Records::new(&b"./src/a.rs\n\n./README.md\n./src/b.rs\n"[..], false);
+        let paths = read_paths(&mut records).unwrap();
+        assert_eq!(paths.len(), 3);
+        let filter = crate::expr::parse("$.rs & !+/b").unwrap();
+        assert_eq!(filter_paths(&filter, Split::Whitespace, paths), [PathBuf::from("./src/a.rs")]);
+
+        let mut records = Records::new(&b"with\nnewline\0x"[..], true);
+        assert_eq!(read_paths(&mut records).unwrap(), [PathBuf::from("with\nnewline"), "x".into()]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn paths_that_arent_utf8() {
+        use std::os::unix::ffi::OsStrExt;
+        let dir = std::env::temp_dir().join(format!("filte-paths-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let name = std::ffi::OsStr::from_bytes(b"caf\xe9.txt");
+        std::fs::write(dir.join(name), "TODO\n").unwrap();
+
+        let mut listed = dir.as_os_str().as_bytes().to_vec();
+        listed.extend(b"/caf\xe9.txt\0");
+        let paths = read_paths(&mut Records::new(&listed[..], true)).unwrap();
+        assert_eq!(paths, [dir.join(name)]);
+        let filter = crate::expr::parse("$.txt").unwrap();
+        let paths = filter_paths(&filter, Split::Whitespace, paths);
+        let search = Search {
+            filter: &filter,
+            split: Split::Whitespace,
+            header: false,
+            output: OutputOptions {
+                null: true,
+                ..Default::default()
+            },
+        };
+        let mut out = Printer::new(vec![], search.output.clone());
+        search.paths(&paths, &mut out).unwrap();
+        assert_eq!(out.finish().unwrap(), listed);
+
+        let filter = crate::expr::parse("+TODO").unwrap();
+        let search = Search { filter: &filter, ..search };
+        assert_eq!(search.files(&paths, &mut vec![]).unwrap(), 0);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn unreadable_files_in_order() {
+        let dir = std::env::temp_dir().join(format!("filte-order-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let big: String = (0..10_000).map(|n| format!("TODO {n}\n")).collect();
+        std::fs::write(dir.join("big.txt"), &big).unwrap();
+        std::fs::write(dir.join("small.txt"), "TODO small\n").unwrap();
+
+        let filter = crate::expr::parse("+TODO").unwrap();
+        let search = Search {
+            filter: &filter,
+            split: Split::Whitespace,
+            header: false,
+            output: OutputOptions {
+                count: true,
+                ..Default::default()
+            },
+        };
+        let files = [dir.join("big.txt"), dir.join("missing.txt"), dir.join("small.txt")];
+        let mut out = vec![];
+        assert_eq!(search.files(&files, &mut out).unwrap(), 1);
+        let d = dir.display();
+        assert_eq!(String::from_utf8(out).unwrap(), format!("{d}/big.txt:10000\n{d}/small.txt:1\n"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
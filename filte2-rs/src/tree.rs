@@ -0,0 +1,212 @@
+use std::fmt::Write;
+use std::sync::Arc;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::*;
+
+// how filters are (de)serialized, raw filters as they're written on the
+// command line and groups tagged with their kind
+//
+//     {"and": [{"or": ["$.rs", "$.toml"]}, {"not": "^target/"}, {"field": [2, ">10"]}]}
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum Tree {
+    Raw(String),
+    Group(Group),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Group {
+    And(Vec<Tree>),
+    Or(Vec<Tree>),
+    Not(Box<Tree>),
+    Field(Column, Box<Tree>),
+    // definitions are kept with their name, they don't need the filters file
+    Named(String, Box<Tree>),
+}
+
+impl From<&Filter> for Tree {
+    fn from(filter: &Filter) -> Self {
+        let all = |rs: &[Filter]| rs.iter().map(Tree::from).collect();
+        Tree::Group(match filter {
+            Filter::Raw(r) => return Tree::Raw(r.to_string()),
+            Filter::And(rs) => Group::And(all(rs)),
+            Filter::Or(rs) => Group::Or(all(rs)),
+            Filter::Not(r) => Group::Not(Box::new(Tree::from(&**r))),
+            Filter::Field(col, r) => Group::Field(col.clone(), Box::new(Tree::from(&**r))),
+            Filter::Named(name, r) => Group::Named(name.clone(), Box::new(Tree::from(&**r))),
+            // back to the group it was merged from
+            Filter::Multi(m) => {
+                let rs = m.filters.iter().map(|r| Tree::Raw(r.to_string())).collect();
+                match m.invert {
+                    true => Group::And(rs),
+                    false => Group::Or(rs),
+                }
+            }
+        })
+    }
+}
+
+impl TryFrom<Tree> for Filter {
+    type Error = FilteError;
+    fn try_from(tree: Tree) -> Result<Self, FilteError> {
+        let all = |rs: Vec<Tree>| rs.into_iter().map(Filter::try_from).collect::<Result<_, _>>();
+        let group = match tree {
+            Tree::Raw(r) => return RawFilter::try_from(r).map(Filter::Raw),
+            Tree::Group(group) => group,
+        };
+        Ok(match group {
+            Group::And(rs) => Filter::And(all(rs)?),
+            Group::Or(rs) => Filter::Or(all(rs)?),
+            Group::Not(r) => Filter::Not(Box::new(Filter::try_from(*r)?)),
+            Group::Field(col, r) => Filter::Field(col, Box::new(Filter::try_from(*r)?)),
+            Group::Named(name, r) => Filter::Named(name, Arc::new(Filter::try_from(*r)?)),
+        })
+    }
+}
+
+impl Serialize for Filter {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Tree::from(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Filter {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let tree = Tree::deserialize(deserializer)?;
+        Filter::try_from(tree).map_err(serde::de::Error::custom)
+    }
+}
+
+impl Filter {
+    // the filter as an indented tree, one filter per line
+    //
+    //     all of
+    //       any of
+    //         ends with ".rs"
+    //         ends with ".toml"
+    //       not
+    //         starts with "target/"
+    pub fn explain(&self) -> String {
+        let mut out = String::new();
+        self.explain_into(&mut out, 0);
+        out
+    }
+
+    fn explain_into(&self, out: &mut String, depth: usize) {
+        let indent = "  ".repeat(depth);
+        let children: Vec<&Filter> = match self {
+            Filter::Raw(r) => {
+                let _ = writeln!(out, "{indent}{}", r.describe());
+                return;
+            }
+            Filter::Multi(m) => {
+                let kind = if m.invert { "all of" } else { "any of" };
+                let _ = writeln!(out, "{indent}{kind}, in one pass");
+                for r in &m.filters {
+                    let _ = writeln!(out, "{indent}  {}", r.describe());
+                }
+                return;
+            }
+            Filter::And(rs) => {
+                let _ = writeln!(out, "{indent}all of");
+                rs.iter().collect()
+            }
+            Filter::Or(rs) => {
+                let _ = writeln!(out, "{indent}any of");
+                rs.iter().collect()
+            }
+            Filter::Not(r) => {
+                let _ = writeln!(out, "{indent}not");
+                vec![r]
+            }
+            Filter::Field(col, r) => {
+                let _ = writeln!(out, "{indent}{col}");
+                vec![r]
+            }
+            Filter::Named(name, r) => {
+                let _ = writeln!(out, "{indent}@{name}");
+                vec![r]
+            }
+        };
+        for child in children {
+            child.explain_into(out, depth + 1);
+        }
+    }
+}
+
+impl RawFilter {
+    fn describe(&self) -> String {
+        let cmp = |cmp: &Cmp| match *cmp {
+            Cmp::Equal(x) => format!("= {x}"),
+            Cmp::Greater(x) => format!("> {x}"),
+            Cmp::Less(x) => format!("< {x}"),
+            Cmp::Range(..) => format!("in {}", &cmp.to_string()[1..]),
+        };
+        let desc = match &self.filter {
+            Mode::Is(rf) => format!("is {rf:?}"),
+            Mode::Starts(rf) => format!("starts with {rf:?}"),
+            Mode::Ends(rf) => format!("ends with {rf:?}"),
+            Mode::Includes(rf) => format!("includes {rf:?}"),
+            // escaping them again would only make them harder to read
+            Mode::Regex(rf) => format!("matches regex \"{}\"", rf.as_str()),
+            Mode::Glob(rf) => format!("matches glob \"{}\"", rf.as_str()),
+            Mode::Number(c) => format!("number {}", cmp(c)),
+            Mode::Length(c) => format!("length {}", cmp(c)),
+            Mode::Fuzzy(rf, max) => {
+                let edits = if *max == 1 { "edit" } else { "edits" };
+                format!("within {max} {edits} of {:?}", rf.iter().collect::<String>())
+            }
+            Mode::Subsequence(rf) => format!("has {:?} in order", rf.iter().collect::<String>()),
+        };
+        let not = if self.invert { "not " } else { "" };
+        let case = if self.ignore_case { ", ignoring case" } else { "" };
+        format!("{not}{desc}{case}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips() {
+        let src = "($.rs | ic.^ab\\d) & !^target/ & col(2)~1..";
+        let f = expr::parse(src).unwrap();
+        let json = serde_json::to_string(&f).unwrap();
+        assert_eq!(
+            json,
+            r#"{"and":[{"or":["$.rs","ic.^ab\\d"]},{"not":"^target/"},{"field":[2,"~1.."]}]}"#
+        );
+        let back: Filter = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&back).unwrap(), json);
+        for line in ["a.rs", "ABC", "aB1", "target/a.rs", "x 3"] {
+            assert_eq!(f.compare(line), back.compare(line), "{line}");
+        }
+
+        let compiled = expr::parse("+a | +b | =c").unwrap().compile();
+        let json = serde_json::to_string(&compiled).unwrap();
+        assert_eq!(json, r#"{"or":["=c",{"or":["+a","+b"]}]}"#);
+        assert!(serde_json::from_str::<Filter>(r#"{"not":".("}"#).is_err());
+    }
+
+    #[test]
+    fn explains() {
+        let f = expr::parse("($.rs | $.toml) & !^target/ & col(size)l>3 & ic+x").unwrap();
+        assert_eq!(
+            f.explain(),
+            "all of
+  any of
+    ends with \".rs\"
+    ends with \".toml\"
+  not
+    starts with \"target/\"
+  col(size)
+    length > 3
+  not includes \"x\", ignoring case
+"
+        );
+    }
+}
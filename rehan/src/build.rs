@@ -1,22 +1,24 @@
 use crate::*;
 
 impl Transform {
-    pub fn transform(&self, value: String) -> Result<String, Error> {
+    pub fn transform(&self, value: Value) -> Result<Value, Error> {
         use Transform::*;
         match self {
-            UpperCaseFirst => Ok(upper_case_first(&value)),
-            AllUpperCase => Ok(value.to_uppercase()),
-            AllLowerCase => Ok(value.to_lowercase()),
+            UpperCaseFirst => Ok(upper_case_first(&value.to_string()).into()),
+            AllUpperCase => Ok(value.to_string().to_uppercase().into()),
+            AllLowerCase => Ok(value.to_string().to_lowercase().into()),
             IsInt => {
-                value.parse::<i128>()?;
+                if !matches!(value, Value::Int(_)) {
+                    value.to_string().parse::<i128>()?;
+                }
                 Ok(value)
             }
             IsNumber => {
-                value.parse::<f64>()?;
+                value.as_f64()?;
                 Ok(value)
             }
             IsGreaterThan(cmp) => {
-                let num = value.parse::<f64>()?;
+                let num = value.as_f64()?;
                 if num > *cmp {
                     Ok(value)
                 } else {
@@ -24,7 +26,7 @@ impl Transform {
                 }
             }
             IsSmallerThan(cmp) => {
-                let num = value.parse::<f64>()?;
+                let num = value.as_f64()?;
                 if num < *cmp {
                     Ok(value)
                 } else {
@@ -32,8 +34,8 @@ impl Transform {
                 }
             }
             IsNumberInRange(cmp_low, cmp_high) => {
-                let num = value.parse::<f64>()?;
-                if num <= *cmp_low && num >= *cmp_high {
+                let num = value.as_f64()?;
+                if num >= *cmp_low && num <= *cmp_high {
                     Ok(value)
                 } else {
                     Err(Error::OutOfRange(num, *cmp_low, *cmp_high))
@@ -48,23 +50,29 @@ impl Directive {
         use Directive::*;
         match self {
             Filename { expr } => {
-                doc.file_name = strfmt(&expr, &doc.vars)?;
+                doc.file_name = doc.render(&expr)?;
             }
-            Input { name, transforms } => {
-                let value = doc
-                    .vars
+            Input {
+                name,
+                ty,
+                transforms,
+            } => {
+                // a missing input still takes its position
+                let n = doc.input_ns;
+                doc.input_ns += 1;
+                let raw = doc
+                    .inputs
                     .get(&name)
-                    .or(doc.vars.get(&doc.input_ns.to_string()))
-                    .ok_or(Error::MissingInput(doc.input_ns, name.clone()))?
+                    .or(doc.inputs.get(&n.to_string()))
+                    .ok_or(Error::MissingInput(n, name.clone()))?
                     .clone();
                 let value = transforms
                     .iter()
-                    .try_fold(value, |v, tra| tra.transform(v))?;
-                doc.input_ns += 1;
+                    .try_fold(ty.check(raw)?, |v, tra| tra.transform(v))?;
                 doc.vars.insert(name, value);
             }
             Format { name, expr } => {
-                doc.vars.insert(name, strfmt(&expr, &doc.vars)?);
+                doc.vars.insert(name, doc.render(&expr)?.into());
             }
             Set {
                 name,
@@ -77,27 +85,287 @@ impl Directive {
                     .try_fold(value, |v, tra| tra.transform(v))?;
                 doc.vars.insert(name, value);
             }
+            Delimiters { open, close } => {
+                doc.delimiters = Some((open, close));
+            }
         }
         Ok(())
     }
 }
 
+impl RuntimeDoc {
+    fn render(&self, expr: &str) -> Result<String, Error> {
+        let Some((open, close)) = &self.delimiters else {
+            return Ok(strfmt(expr, &self.vars)?);
+        };
+        // with custom delimiters, braces outside placeholders are literal
+        let mut out = String::with_capacity(expr.len());
+        let mut rest = expr;
+        while let Some(start) = rest.find(open.as_str()) {
+            out += &rest[..start];
+            rest = &rest[start + open.len()..];
+            let end = rest
+                .find(close.as_str())
+                .ok_or(Error::UnclosedDelimiter(open.clone()))?;
+            out += &strfmt(&format!("{{{}}}", rest[..end].trim()), &self.vars)?;
+            rest = &rest[end + close.len()..];
+        }
+        out += rest;
+        Ok(out)
+    }
+}
+
+impl RuntimeDoc {
+    // the variables an expression uses, `{name}` or `{name:spec}`
+    fn references<'a>(&self, expr: &'a str) -> Vec<&'a str> {
+        let (open, close) = match &self.delimiters {
+            Some((open, close)) => (open.as_str(), close.as_str()),
+            None => ("{", "}"),
+        };
+        let mut out = vec![];
+        let mut rest = expr;
+        while let Some(start) = rest.find(open) {
+            rest = &rest[start + open.len()..];
+            // `{{` is an escaped brace with strfmt's syntax
+            if self.delimiters.is_none() && rest.starts_with('{') {
+                rest = &rest[1..];
+                continue;
+            }
+            let Some(end) = rest.find(close) else {
+                break;
+            };
+            out.push(rest[..end].split(':').next().unwrap_or_default().trim());
+            rest = &rest[end + close.len()..];
+        }
+        out
+    }
+}
+
 impl RawDocument {
-    pub fn format(self, inputs: Vars) -> Result<Document, Error> {
-        let directives = self.directives;
+    pub fn format(self, inputs: Inputs) -> Result<Document, Error> {
+        let mut directives = self.directives;
+        // delimiters apply to every expression, wherever they're declared
+        directives.sort_by_key(|(_, d)| !matches!(d, Directive::Delimiters { .. }));
         let original_content = self.actual_content;
         let mut doc = RuntimeDoc {
             input_ns: 0,
             file_name: self.file_name,
-            vars: inputs,
+            vars: inputs
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone().into()))
+                .collect(),
+            inputs,
+            delimiters: None,
         };
 
-        for directive in directives {
-            directive.act(&mut doc)?;
+        // keep going after a failed directive so every validation error is
+        // reported at once; directives that use a variable that failed are skipped
+        let mut errors = vec![];
+        let mut failed = HashSet::new();
+        for (line, directive) in directives {
+            let depends_on_failure = match &directive {
+                Directive::Set { from, .. } => failed.contains(from),
+                Directive::Format { expr, .. } | Directive::Filename { expr } => {
+                    doc.references(expr).into_iter().any(|v| failed.contains(v))
+                }
+                Directive::Input { .. } | Directive::Delimiters { .. } => false,
+            };
+            if depends_on_failure {
+                failed.extend(directive.target().map(str::to_string));
+                continue;
+            }
+            let context = directive.to_string();
+            let target = directive.target().map(str::to_string);
+            if let Err(e) = directive.act(&mut doc) {
+                failed.extend(target);
+                errors.push(Error::AtLine {
+                    line,
+                    directive: context,
+                    source: Box::new(e),
+                });
+            }
+        }
+        if !errors.is_empty() {
+            return Err(Error::Validation(errors));
         }
         Ok(Document {
             file_name: doc.file_name.clone(),
-            content: strfmt(&original_content, &doc.vars)?,
+            content: doc.render(&original_content)?,
         })
     }
 }
+
+impl Directive {
+    // name of the variable defined by this directive, if any
+    fn target(&self) -> Option<&str> {
+        use Directive::*;
+        match self {
+            Filename { .. } | Delimiters { .. } => None,
+            Input { name, .. } | Format { name, .. } | Set { name, .. } => Some(name),
+        }
+    }
+}
+
+impl std::fmt::Display for Directive {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        use Directive::*;
+        match self {
+            Filename { .. } => write!(f, "#filename"),
+            Input { name, .. } => write!(f, "#input {name}"),
+            Format { name, .. } => write!(f, "#format {name}"),
+            Set { name, .. } => write!(f, "#set {name}"),
+            Delimiters { .. } => write!(f, "#delimiters"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse_directive;
+
+    fn format(
+        directives: &[&str],
+        content: &str,
+        inputs: &[(&str, &str)],
+    ) -> Result<Document, Error> {
+        let directives = directives
+            .iter()
+            .enumerate()
+            .map(|(n, line)| (n + 1, parse_directive(line).unwrap().unwrap()))
+            .collect();
+        let raw = RawDocument {
+            file_name: "out".to_string(),
+            directives,
+            actual_content: content.to_string(),
+        };
+        let inputs = inputs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        raw.format(inputs)
+    }
+
+    #[test]
+    fn delimiters() {
+        let doc = format(
+            &[
+                "#input title",
+                "#filename {<% title %>}.tex",
+                "#format heading \\section{<% title %>}",
+                "#delimiters <% %>",
+            ],
+            "<% heading %>{} <%title%>",
+            &[("title", "Intro")],
+        )
+        .unwrap();
+        assert_eq!(doc.file_name, "{Intro}.tex");
+        assert_eq!(doc.content, "\\section{Intro}{} Intro");
+
+        let err = format(
+            &["#input a", "#delimiters [[ ]]"],
+            "[[ a ]] [[ a",
+            &[("a", "1")],
+        );
+        assert!(matches!(err, Err(Error::UnclosedDelimiter(open)) if open == "[["));
+    }
+
+    #[test]
+    fn range_boundaries() {
+        let range = Transform::IsNumberInRange(1.0, 10.0);
+        for ok in [1.0, 5.5, 10.0] {
+            assert!(range.transform(Value::Float(ok)).is_ok(), "{ok}");
+        }
+        for out in [0.99, 10.01, -1.0] {
+            assert!(range.transform(Value::Float(out)).is_err(), "{out}");
+        }
+        assert!(Transform::IsGreaterThan(1.0)
+            .transform(Value::Int(1))
+            .is_err());
+        assert!(Transform::IsSmallerThan(1.0)
+            .transform(Value::Int(0))
+            .is_ok());
+    }
+
+    #[test]
+    fn collects_validation_errors() {
+        let err = format(
+            &[
+                "#input n:int",
+                "#input when:date",
+                "#input price:float [IsNumberInRange(1, 10)]",
+                "#format label {n}-{when}",
+            ],
+            "{label} {price}",
+            &[("n", "x"), ("when", "2024-02-30"), ("price", "11")],
+        )
+        .unwrap_err();
+        let Error::Validation(errors) = &err else {
+            panic!("{err:?}");
+        };
+        // label only uses failed inputs, it's skipped rather than reported
+        let lines: Vec<_> = errors
+            .iter()
+            .map(|e| match e {
+                Error::AtLine { line, .. } => *line,
+                e => panic!("{e:?}"),
+            })
+            .collect();
+        assert_eq!(lines, [1, 2, 3]);
+        assert!(err.to_string().starts_with("line 1: #input n: "));
+    }
+
+    #[test]
+    fn formats_that_dont_use_a_failed_input_still_run() {
+        let err = format(
+            &[
+                "#input n:int",
+                "#input name",
+                "#format title {name}",
+                "#format oops {missing}",
+            ],
+            "",
+            &[("n", "x"), ("name", "a")],
+        )
+        .unwrap_err();
+        let Error::Validation(errors) = err else {
+            panic!();
+        };
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(errors[1], Error::AtLine { line: 4, .. }));
+
+        let doc = format(
+            &[
+                "#input n:int",
+                "#filename {n}.txt",
+                "#format twice {n}{{{n}}}",
+            ],
+            "{twice}",
+            &[("n", " 4 ")],
+        )
+        .unwrap();
+        assert_eq!(
+            (doc.file_name.as_str(), doc.content.as_str()),
+            ("4.txt", "4{4}")
+        );
+    }
+
+    #[test]
+    fn missing_inputs_keep_their_position() {
+        let err = format(&["#input a", "#input b"], "{a} {b}", &[("1", "B")]).unwrap_err();
+        let Error::Validation(errors) = &err else {
+            panic!("{err:?}");
+        };
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(&errors[0], Error::AtLine { source, .. }
+            if matches!(**source, Error::MissingInput(0, ref name) if name == "a")));
+
+        let doc = format(
+            &["#input a", "#input b"],
+            "{a} {b}",
+            &[("0", "A"), ("1", "B")],
+        )
+        .unwrap();
+        assert_eq!(doc.content, "A B");
+    }
+}
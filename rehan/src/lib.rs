@@ -1,9 +1,11 @@
-pub use std::collections::HashMap;
+pub use std::collections::{HashMap, HashSet};
 pub use strfmt::strfmt;
-pub type Vars = HashMap<String, String>;
+pub type Inputs = HashMap<String, String>;
+pub type Vars = HashMap<String, Value>;
 pub mod build;
 pub mod parse;
 mod reader;
+mod value;
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -25,6 +27,18 @@ pub enum Error {
     NotSmallerThan(f64, f64),
     #[error("Value {0} is out range [{1}, {2}]")]
     OutOfRange(f64, f64, f64),
+    #[error("Value {0:?} is not a valid {1}")]
+    WrongType(String, Type),
+    #[error("Unkown type {0}")]
+    UnkownType(String),
+    #[error("line {line}: {directive}: {source}")]
+    AtLine {
+        line: usize,
+        directive: String,
+        source: Box<Error>,
+    },
+    #[error("{}", join_errors(.0))]
+    Validation(Vec<Error>),
     #[error("Unkown Directive {0}")]
     UnkownDirective(String),
     #[error("Directive {0} needs more arguments")]
@@ -35,6 +49,8 @@ pub enum Error {
     UnkownTransform(String),
     #[error("Missing file argument")]
     MissingFile,
+    #[error("Placeholder opened with {0} is never closed")]
+    UnclosedDelimiter(String),
 }
 
 #[derive(Debug)]
@@ -49,6 +65,25 @@ pub enum Transform {
     IsNumberInRange(f64, f64),
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    Str,
+    Int,
+    Float,
+    Bool,
+    Date,
+    Enum(Vec<String>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Str(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Date { year: u16, month: u8, day: u8 },
+}
+
 #[derive(Debug)]
 pub enum Directive {
     Filename {
@@ -56,6 +91,7 @@ pub enum Directive {
     },
     Input {
         name: String,
+        ty: Type,
         transforms: Vec<Transform>,
     },
     Format {
@@ -67,14 +103,18 @@ pub enum Directive {
         from: String,
         transforms: Vec<Transform>,
     },
+    Delimiters {
+        open: String,
+        close: String,
+    },
 }
 
 // parse .rehen. file into RawDoc
 #[derive(Debug)]
 pub struct RawDocument {
     pub file_name: String,
-    // directives defines for document
-    pub directives: Vec<Directive>,
+    // directives defines for document, with their source line
+    pub directives: Vec<(usize, Directive)>,
     // content before rehan processing
     pub actual_content: String,
 }
@@ -84,7 +124,10 @@ pub struct RawDocument {
 pub struct RuntimeDoc {
     pub file_name: String,
     pub vars: Vars,
+    inputs: Inputs,
     input_ns: usize,
+    // custom placeholder delimiters, strfmt's {} otherwise
+    delimiters: Option<(String, String)>,
 }
 
 // document is built after all directives are executed
@@ -94,6 +137,14 @@ pub struct Document {
     pub content: String,
 }
 
+fn join_errors(errors: &[Error]) -> String {
+    errors
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 fn upper_case_first(s: &str) -> String {
     let mut c = s.chars();
     match c.next() {
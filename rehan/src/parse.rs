@@ -44,7 +44,7 @@ fn parse_transforms(line: &str) -> Result<Vec<Transform>, Error> {
         .collect()
 }
 
-fn parse_directive(line: &str) -> Result<Option<Directive>, Error> {
+pub(crate) fn parse_directive(line: &str) -> Result<Option<Directive>, Error> {
     use Directive::*;
     use Error::*;
     let mut line = line.splitn(2, " ");
@@ -68,8 +68,14 @@ fn parse_directive(line: &str) -> Result<Option<Directive>, Error> {
                 .ok_or(DirectiveMissingArgs(directive_name.to_string()))
                 // in case of no transforms in directive
                 .unwrap_or((expr, Ok(vec![])));
+            // typed inputs are declared as name:type
+            let (name, ty) = match name.split_once(":") {
+                Some((name, ty)) => (name, ty.parse()?),
+                None => (name, Type::Str),
+            };
             Input {
                 name: name.to_string(),
+                ty,
                 transforms: transforms?,
             }
         }
@@ -93,7 +99,17 @@ fn parse_directive(line: &str) -> Result<Option<Directive>, Error> {
                 transforms: parse_transforms(transforms)?,
             }
         }
-        ("#filename" | "#input" | "#format" | "#set", None) => {
+        ("#delimiters", Some(expr)) => {
+            let (open, close) = expr
+                .trim()
+                .split_once(" ")
+                .ok_or(DirectiveMissingArgs(directive_name.to_string()))?;
+            Delimiters {
+                open: open.to_string(),
+                close: close.trim().to_string(),
+            }
+        }
+        ("#filename" | "#input" | "#format" | "#set" | "#delimiters", None) => {
             return Err(DirectiveMissingArgs(directive_name.to_string()))
         }
         (x, _) => return Err(UnkownDirective(x.to_string())),
@@ -104,17 +120,29 @@ pub fn parse_doc(file_name: String) -> Result<RawDocument, Error> {
     let mut reader = reader::BufReader::open(&file_name)?;
     let mut buffer = String::new();
     let mut content = String::new();
-    let mut directives = Vec::<Directive>::new();
+    let mut directives = Vec::<(usize, Directive)>::new();
+    let mut errors = vec![];
+    let mut line_n = 0;
 
     while let Some(line) = reader.read_line(&mut buffer) {
         let line = line?.trim();
+        line_n += 1;
         if line == "#done" {
             break;
         }
-        if let Some(directive) = parse_directive(line)? {
-            directives.push(directive);
+        match parse_directive(line) {
+            Ok(Some(directive)) => directives.push((line_n, directive)),
+            Ok(None) => (),
+            Err(e) => errors.push(Error::AtLine {
+                line: line_n,
+                directive: line.split(" ").next().unwrap_or_default().to_string(),
+                source: Box::new(e),
+            }),
         }
     }
+    if !errors.is_empty() {
+        return Err(Error::Validation(errors));
+    }
 
     while let Some(line) = reader.read_line(&mut buffer) {
         content += line?;
@@ -127,7 +155,7 @@ pub fn parse_doc(file_name: String) -> Result<RawDocument, Error> {
     })
 }
 
-pub fn parse_args(args: impl Iterator<Item=String>) -> Result<Vars, Error> {
+pub fn parse_args(args: impl Iterator<Item=String>) -> Result<Inputs, Error> {
     let mut vars = HashMap::new();
     for (n, arg) in args.enumerate() {
         if let Some((name, val)) = arg.split_once(":") {
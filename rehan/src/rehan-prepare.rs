@@ -2,7 +2,7 @@ use rehan::*;
 use std::io::Write;
 use std::{fs, path};
 
-fn make_rehan_filename(origin: &path::PathBuf) -> path::PathBuf {
+fn make_rehan_filename(origin: &path::Path) -> path::PathBuf {
     let mut ext = std::ffi::OsString::from("rehan.");
     ext.push(origin.extension().unwrap_or(std::ffi::OsStr::new("")));
     origin.with_extension(ext)
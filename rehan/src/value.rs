@@ -0,0 +1,153 @@
+use crate::*;
+use std::fmt;
+use strfmt::{DisplayStr, Formatter};
+
+impl std::str::FromStr for Type {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use Type::*;
+        Ok(match s {
+            "str" => Str,
+            "int" => Int,
+            "float" => Float,
+            "bool" => Bool,
+            "date" => Date,
+            _ => {
+                let options = s
+                    .strip_prefix("enum(")
+                    .and_then(|s| s.strip_suffix(")"))
+                    .ok_or(Error::UnkownType(s.to_string()))?;
+                Enum(options.split('|').map(str::to_string).collect())
+            }
+        })
+    }
+}
+
+impl Type {
+    pub fn check(&self, raw: String) -> Result<Value, Error> {
+        let wrong = || Error::WrongType(raw.clone(), self.clone());
+        Ok(match self {
+            Type::Str => Value::Str(raw),
+            Type::Int => Value::Int(raw.trim().parse().map_err(|_| wrong())?),
+            Type::Float => Value::Float(raw.trim().parse().map_err(|_| wrong())?),
+            Type::Bool => match raw.trim().to_lowercase().as_str() {
+                "true" | "yes" | "y" | "1" => Value::Bool(true),
+                "false" | "no" | "n" | "0" => Value::Bool(false),
+                _ => return Err(wrong()),
+            },
+            Type::Date => parse_date(raw.trim()).ok_or_else(wrong)?,
+            Type::Enum(options) => match options.iter().find(|o| *o == raw.trim()) {
+                Some(option) => Value::Str(option.clone()),
+                None => return Err(wrong()),
+            },
+        })
+    }
+}
+
+// expects YYYY-MM-DD
+fn parse_date(s: &str) -> Option<Value> {
+    let mut parts = s.splitn(3, '-');
+    let year: u16 = parts.next()?.parse().ok()?;
+    let month: u8 = parts.next()?.parse().ok()?;
+    let day: u8 = parts.next()?.parse().ok()?;
+    let leap = year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400));
+    let month_len = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return None,
+    };
+    (1..=month_len)
+        .contains(&day)
+        .then_some(Value::Date { year, month, day })
+}
+
+impl Value {
+    pub fn as_f64(&self) -> Result<f64, Error> {
+        Ok(match self {
+            Value::Int(i) => *i as f64,
+            Value::Float(f) => *f,
+            other => other.to_string().parse()?,
+        })
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::Str(s)
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Type::Str => write!(f, "str"),
+            Type::Int => write!(f, "int"),
+            Type::Float => write!(f, "float"),
+            Type::Bool => write!(f, "bool"),
+            Type::Date => write!(f, "date"),
+            Type::Enum(options) => write!(f, "enum({})", options.join("|")),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Str(s) => write!(f, "{s}"),
+            Value::Int(i) => write!(f, "{i}"),
+            Value::Float(x) => write!(f, "{x}"),
+            Value::Bool(b) => write!(f, "{b}"),
+            Value::Date { year, month, day } => write!(f, "{year:04}-{month:02}-{day:02}"),
+        }
+    }
+}
+
+// lets templates use format specs, like {price:.2}, on numeric values
+impl DisplayStr for Value {
+    fn display_str(&self, f: &mut Formatter) -> strfmt::Result<()> {
+        match self {
+            Value::Int(i) => f.i64(*i),
+            Value::Float(x) => f.f64(*x),
+            other => f.str(&other.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(ty: &str, raw: &str) -> Result<Value, Error> {
+        ty.parse::<Type>().unwrap().check(raw.to_string())
+    }
+
+    #[test]
+    fn types() {
+        assert_eq!(check("str", " a b ").unwrap(), Value::Str(" a b ".into()));
+        assert_eq!(check("int", " -42 ").unwrap(), Value::Int(-42));
+        assert!(check("int", "4.2").is_err());
+        assert_eq!(check("float", "4.5").unwrap(), Value::Float(4.5));
+        assert!(check("float", "four").is_err());
+        assert_eq!(check("bool", " Yes").unwrap(), Value::Bool(true));
+        assert_eq!(check("bool", "0").unwrap(), Value::Bool(false));
+        assert!(check("bool", "maybe").is_err());
+        assert_eq!(check("enum(a|b)", " b ").unwrap(), Value::Str("b".into()));
+        assert!(check("enum(a|b)", "c").is_err());
+        assert!("list".parse::<Type>().is_err());
+    }
+
+    #[test]
+    fn dates() {
+        let date = |s| check("date", s).map(|v| v.to_string());
+        assert_eq!(date(" 2024-02-29").unwrap(), "2024-02-29");
+        assert_eq!(date("2024-4-1").unwrap(), "2024-04-01");
+        assert!(date("2023-02-29").is_err());
+        assert!(date("1900-02-29").is_err());
+        assert!(date("2000-02-29").is_ok());
+        assert!(date("2024-13-01").is_err());
+        assert!(date("2024-04-31").is_err());
+        assert!(date("2024-04").is_err());
+    }
+}
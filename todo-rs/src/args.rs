@@ -0,0 +1,198 @@
+use crate::changes::Since;
+use crate::TDError;
+use std::path::PathBuf;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Output {
+    #[default]
+    Tree,
+    Json,
+    Text,
+    Sarif,
+    Markdown,
+    Csv,
+    Stats,
+}
+
+impl std::str::FromStr for Output {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "tree" => Output::Tree,
+            "json" => Output::Json,
+            "text" => Output::Text,
+            "sarif" => Output::Sarif,
+            "md" | "markdown" => Output::Markdown,
+            "csv" => Output::Csv,
+            "stats" => Output::Stats,
+            _ => return Err(()),
+        })
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SortBy {
+    #[default]
+    Position,
+    // oldest TODOs first
+    Age,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum Command {
+    // report TODOs
+    #[default]
+    Scan,
+    // show which configs, patterns and finders apply to a path
+    Explain(PathBuf),
+    // write a starter config, in the current directory or the global one
+    Init { global: bool },
+    // send TODOs to a webhook and/or write issue stubs, overriding the config
+    Export { url: Option<String>, dir: Option<PathBuf> },
+    // browse the TODOs interactively
+    Browse,
+    // print the totals from the history file
+    Trend { sparkline: bool },
+}
+
+#[derive(Debug)]
+pub struct Args {
+    pub command: Command,
+    pub output: Output,
+    pub blame: bool,
+    pub older_than: Option<i64>,
+    pub sort: SortBy,
+    pub context: usize,
+    pub owner: Option<String>,
+    pub overdue: bool,
+    pub priority: Option<u8>,
+    // JSON report from a previous run, fail if there are TODOs not in it
+    pub baseline: Option<PathBuf>,
+    pub jobs: usize,
+    // overrides the config's respect_gitignore
+    pub respect_gitignore: Option<bool>,
+    // only scan files changed since a revision or staged
+    pub since: Option<Since>,
+    // only report TODOs on lines added in those changes
+    pub added_only: bool,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Args {
+            command: Command::default(),
+            output: Output::default(),
+            blame: false,
+            older_than: None,
+            sort: SortBy::default(),
+            context: 0,
+            owner: None,
+            overdue: false,
+            priority: None,
+            baseline: None,
+            jobs: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            respect_gitignore: None,
+            since: None,
+            added_only: false,
+        }
+    }
+}
+
+impl Args {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, TDError> {
+        let mut out = Args::default();
+        let mut args = args.into_iter().peekable();
+        if args.peek().is_some_and(|a| a == "config") {
+            args.next();
+            let flag = value("config", args.next())?;
+            if flag != "--explain" {
+                return Err(TDError::UnknownArg(flag));
+            }
+            out.command = Command::Explain(value(&flag, args.next())?.into());
+        } else if args.peek().is_some_and(|a| a == "init") {
+            args.next();
+            let global = args.next_if(|a| a == "--global").is_some();
+            out.command = Command::Init { global };
+        } else if args.peek().is_some_and(|a| a == "export") {
+            args.next();
+            let (mut url, mut dir) = (None, None);
+            while let Some(flag) = args.next_if(|a| a == "--url" || a == "--dir") {
+                match flag.as_str() {
+                    "--url" => url = Some(value(&flag, args.next())?),
+                    _ => dir = Some(value(&flag, args.next())?.into()),
+                }
+            }
+            out.command = Command::Export { url, dir };
+        } else if args.next_if(|a| a == "browse").is_some() {
+            out.command = Command::Browse;
+        } else if args.next_if(|a| a == "trend").is_some() {
+            let sparkline = args.next_if(|a| a == "--sparkline").is_some();
+            out.command = Command::Trend { sparkline };
+        }
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--tree" => out.output = Output::Tree,
+                "--json" => out.output = Output::Json,
+                "--text" => out.output = Output::Text,
+                "--stats" => out.output = Output::Stats,
+                "--format" => {
+                    let format = value(&arg, args.next())?;
+                    out.output = parse_value(&arg, &format)?;
+                }
+                "--blame" => out.blame = true,
+                "--older-than" => {
+                    let days = value(&arg, args.next())?;
+                    out.older_than = Some(parse_value(&arg, &days)?);
+                    // age is only known through blame
+                    out.blame = true;
+                }
+                "--sort" => {
+                    out.sort = match value(&arg, args.next())?.as_str() {
+                        "pos" => SortBy::Position,
+                        "age" => {
+                            out.blame = true;
+                            SortBy::Age
+                        }
+                        x => return Err(TDError::InvalidArgValue(arg, x.to_string())),
+                    }
+                }
+                "-C" | "--context" => {
+                    let lines = value(&arg, args.next())?;
+                    out.context = parse_value(&arg, &lines)?;
+                }
+                "--owner" => out.owner = Some(value(&arg, args.next())?),
+                "--overdue" => out.overdue = true,
+                "--priority" => {
+                    let priority = value(&arg, args.next())?;
+                    let level = priority.trim_start_matches(['p', 'P']);
+                    out.priority = Some(parse_value(&arg, level)?);
+                }
+                "--baseline" => out.baseline = Some(value(&arg, args.next())?.into()),
+                "-j" | "--jobs" => {
+                    let jobs = value(&arg, args.next())?;
+                    out.jobs = parse_value(&arg, &jobs)?;
+                }
+                "--gitignore" => out.respect_gitignore = Some(true),
+                "--no-gitignore" => out.respect_gitignore = Some(false),
+                "--since" => out.since = Some(Since::Rev(value(&arg, args.next())?)),
+                "--staged" => out.since = Some(Since::Staged),
+                "--added-only" => out.added_only = true,
+                _ => return Err(TDError::UnknownArg(arg)),
+            }
+        }
+        if out.added_only && out.since.is_none() {
+            return Err(TDError::AddedOnlyWithoutDiff);
+        }
+        Ok(out)
+    }
+}
+
+fn value(arg: &str, value: Option<String>) -> Result<String, TDError> {
+    value.ok_or(TDError::MissingArgValue(arg.to_string()))
+}
+
+fn parse_value<T: std::str::FromStr>(arg: &str, value: &str) -> Result<T, TDError> {
+    value
+        .parse()
+        .map_err(|_| TDError::InvalidArgValue(arg.to_string(), value.to_string()))
+}
@@ -0,0 +1,125 @@
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+use crate::TDError;
+
+// a TODO as read back from a JSON report
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub path: String,
+    pub line: u64,
+    pub col: u64,
+    pub text: String,
+}
+
+// a baseline that doesn't exist yet has no TODOs
+pub fn load(path: impl AsRef<Path>) -> Result<Value, TDError> {
+    let text = match std::fs::read_to_string(path) {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Value::Object(Default::default())),
+        text => text?,
+    };
+    Ok(serde_json::from_str(&text)?)
+}
+
+// TODOs in `current` that aren't in `baseline`. TODOs are matched by file and
+// text, so moving a TODO around in the same file doesn't make it new
+pub fn new_todos(baseline: &Value, current: &Value) -> Vec<Entry> {
+    let mut known: HashMap<(String, String), usize> = HashMap::new();
+    for e in entries(baseline) {
+        *known.entry((e.path, e.text)).or_default() += 1;
+    }
+    entries(current)
+        .into_iter()
+        .filter(|e| {
+            match known.get_mut(&(e.path.clone(), e.text.clone())) {
+                Some(n) if *n > 0 => {
+                    *n -= 1;
+                    false
+                }
+                _ => true,
+            }
+        })
+        .collect()
+}
+
+// flatten a JSON report, directories are objects and files are arrays of TODOs
+pub fn entries(report: &Value) -> Vec<Entry> {
+    let mut out = vec![];
+    // the root directory is not part of the reported paths
+    if let Value::Object(root) = report {
+        for dir in root.values() {
+            walk(dir, "", &mut out);
+        }
+    }
+    out
+}
+
+fn walk(node: &Value, path: &str, out: &mut Vec<Entry>) {
+    match node {
+        Value::Object(children) => {
+            let mut children: Vec<_> = children.iter().collect();
+            children.sort_by_key(|(name, _)| *name);
+            for (name, child) in children {
+                let path = match path {
+                    "" => name.clone(),
+                    _ => format!("{path}/{name}"),
+                };
+                walk(child, &path, out);
+            }
+        }
+        Value::Array(todos) => {
+            out.extend(todos.iter().map(|t| Entry {
+                path: path.to_string(),
+                line: t["line"].as_u64().unwrap_or(0),
+                col: t["col"].as_u64().unwrap_or(0),
+                text: t["text"].as_str().unwrap_or_default().to_string(),
+            }));
+        }
+        _ => (),
+    }
+}
+
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}: {}", self.path, self.line, self.col, self.text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn todo(line: u64, text: &str) -> Value {
+        json!({"line": line, "col": 1, "text": text})
+    }
+
+    #[test]
+    fn new_todos_match_by_file_and_text() {
+        let baseline = json!({".": {"src": {"a.rs": [todo(1, "TODO: one"), todo(5, "TODO: two")]}}});
+        // moved in the same file
+        let current = json!({".": {"src": {"a.rs": [todo(3, "TODO: two"), todo(9, "TODO: one")]}}});
+        assert!(new_todos(&baseline, &current).is_empty());
+
+        // moved to another file
+        let current = json!({".": {"src": {"a.rs": [todo(1, "TODO: one")], "b.rs": [todo(5, "TODO: two")]}}});
+        let new = new_todos(&baseline, &current);
+        assert_eq!(new.len(), 1);
+        assert_eq!(new[0].to_string(), "src/b.rs:5:1: TODO: two");
+    }
+
+    #[test]
+    fn new_todos_count_duplicates() {
+        let baseline = json!({".": {"a.rs": [todo(1, "TODO")]}});
+        let current = json!({".": {"a.rs": [todo(1, "TODO"), todo(2, "TODO")]}});
+        let new = new_todos(&baseline, &current);
+        assert_eq!(new.len(), 1);
+        assert_eq!(new[0].line, 2);
+
+        let empty = load("does/not/exist.json").unwrap();
+        assert_eq!(new_todos(&empty, &current).len(), 2);
+        assert!(new_todos(&current, &empty).is_empty());
+    }
+}
@@ -0,0 +1,134 @@
+use git2::Repository;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::TDError;
+
+const SECS_IN_DAY: i64 = 60 * 60 * 24;
+// author of lines that are only in the working tree, like `git blame`
+pub const NOT_COMMITTED: &str = "Not Committed Yet";
+
+#[derive(Debug, Serialize, Hash, PartialEq, Eq, Clone)]
+pub struct Blame {
+    pub author: String,
+    pub commit: String,
+    // unix timestamp of the commit that last touched the line
+    pub time: i64,
+    pub age_days: i64,
+}
+
+pub struct Blamer {
+    repo: Repository,
+    workdir: PathBuf,
+    now: i64,
+}
+
+impl Blamer {
+    pub fn discover(path: impl AsRef<Path>) -> Result<Self, TDError> {
+        let repo = Repository::discover(path)?;
+        let workdir = repo
+            .workdir()
+            .ok_or(TDError::BareRepository)?
+            .canonicalize()?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        Ok(Blamer { repo, workdir, now })
+    }
+
+    // blame each line (1-indexed) of a file as it is in the working tree, lines
+    // that aren't committed yet are attributed to NOT_COMMITTED
+    pub fn blame_lines(
+        &self,
+        path: &Path,
+        lines: impl IntoIterator<Item = usize>,
+    ) -> Result<Vec<Blame>, TDError> {
+        self.blame_contents(path, &std::fs::read(path)?, lines)
+    }
+
+    // same as blame_lines, for other contents of the file, like the staged ones
+    pub fn blame_contents(
+        &self,
+        path: &Path,
+        contents: &[u8],
+        lines: impl IntoIterator<Item = usize>,
+    ) -> Result<Vec<Blame>, TDError> {
+        let relative = path.canonicalize()?;
+        let relative = relative
+            .strip_prefix(&self.workdir)
+            .map_err(|_| TDError::OutsideRepository(path.to_path_buf()))?;
+        let head = match self.repo.blame_file(relative, None) {
+            Ok(blame) => blame,
+            // untracked files have no history
+            Err(e) if e.code() == git2::ErrorCode::NotFound => {
+                return Ok(lines.into_iter().map(|_| self.not_committed()).collect())
+            }
+            Err(e) => return Err(e.into()),
+        };
+        // the lines were found in `contents`, HEAD may have them elsewhere
+        let blame = head.blame_buffer(contents)?;
+        Ok(lines
+            .into_iter()
+            .map(|line| {
+                let Some(hunk) = blame.get_line(line) else {
+                    return self.not_committed();
+                };
+                let commit = hunk.final_commit_id();
+                if commit.is_zero() {
+                    return self.not_committed();
+                }
+                let sig = hunk.final_signature();
+                let time = sig.when().seconds();
+                Blame {
+                    author: sig.name().unwrap_or("unknown").to_string(),
+                    commit: commit.to_string(),
+                    time,
+                    age_days: (self.now - time) / SECS_IN_DAY,
+                }
+            })
+            .collect())
+    }
+
+    fn not_committed(&self) -> Blame {
+        Blame {
+            author: NOT_COMMITTED.to_string(),
+            commit: git2::Oid::zero().to_string(),
+            time: self.now,
+            age_days: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::Signature;
+
+    #[test]
+    fn blames_the_working_tree() {
+        let dir = std::env::temp_dir().join(format!("todo-rs-blame-{}", std::process::id()));
+        let repo = Repository::init(&dir).unwrap();
+        let file = dir.join("a.rs");
+        std::fs::write(&file, "fn a() {}\n// TODO: old\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("a.rs")).unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = Signature::now("Ann", "ann@example.com").unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "first", &tree, &[]).unwrap();
+
+        // uncommitted lines above the old TODO move it down
+        std::fs::write(&file, "// TODO: new\n\nfn a() {}\n// TODO: old\n").unwrap();
+        let blamer = Blamer::discover(&dir).unwrap();
+        let blames = blamer.blame_lines(&file, [1, 4]).unwrap();
+        assert_eq!(blames[0].author, NOT_COMMITTED);
+        assert_eq!(blames[1].author, "Ann");
+        assert_eq!(blames[1].age_days, 0);
+
+        std::fs::write(dir.join("b.rs"), "// TODO\n").unwrap();
+        let blames = blamer.blame_lines(&dir.join("b.rs"), [1]).unwrap();
+        assert_eq!(blames[0].author, NOT_COMMITTED);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,188 @@
+use git2::{Delta, DiffOptions, Repository};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::filstu::PathFilter;
+use crate::TDError;
+
+// what to compare the working tree against
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Since {
+    // a revision, everything changed since it (commited or not, untracked included)
+    Rev(String),
+    // HEAD against the index, what the next commit would change
+    Staged,
+}
+
+// files changed in a diff and the lines added to them
+#[derive(Debug, Default)]
+pub struct Changes {
+    // keyed like the walked paths, relative to the current directory with a leading "."
+    files: HashMap<PathBuf, HashSet<usize>>,
+    // with Since::Staged, the staged contents of the changed files
+    staged: HashMap<PathBuf, Vec<u8>>,
+}
+
+impl Changes {
+    // changes in the repo containing the current directory, files outside of
+    // the current directory are left out
+    pub fn discover(since: &Since) -> Result<Self, TDError> {
+        Self::discover_in(Path::new("."), since)
+    }
+
+    // same as discover, from `dir` instead of the current directory
+    pub fn discover_in(dir: &Path, since: &Since) -> Result<Self, TDError> {
+        let repo = Repository::discover(dir)?;
+        let workdir = repo
+            .workdir()
+            .ok_or(TDError::BareRepository)?
+            .canonicalize()?;
+        let cwd = dir.canonicalize()?;
+        let prefix = cwd
+            .strip_prefix(&workdir)
+            .map_err(|_| TDError::OutsideRepository(cwd.clone()))?;
+
+        let mut opts = DiffOptions::new();
+        let diff = match since {
+            Since::Rev(rev) => {
+                let tree = repo.revparse_single(rev)?.peel_to_tree()?;
+                opts.include_untracked(true)
+                    .recurse_untracked_dirs(true)
+                    .show_untracked_content(true);
+                repo.diff_tree_to_workdir_with_index(Some(&tree), Some(&mut opts))?
+            }
+            Since::Staged => {
+                // there's no HEAD before the first commit
+                let head = repo.head().ok().and_then(|h| h.peel_to_tree().ok());
+                repo.diff_tree_to_index(head.as_ref(), None, Some(&mut opts))?
+            }
+        };
+
+        let mut changed: HashMap<PathBuf, HashSet<usize>> = diff
+            .deltas()
+            .filter(|d| d.status() != Delta::Deleted)
+            .filter_map(|d| d.new_file().path().map(|p| (p.to_path_buf(), HashSet::new())))
+            .collect();
+        diff.foreach(
+            &mut |_, _| true,
+            None,
+            None,
+            Some(&mut |delta, _, line| {
+                if let (Some(path), Some(n)) = (delta.new_file().path(), line.new_lineno()) {
+                    if line.origin() == '+' {
+                        changed.entry(path.to_path_buf()).or_default().insert(n as usize);
+                    }
+                }
+                true
+            }),
+        )?;
+
+        let mut staged = HashMap::new();
+        if *since == Since::Staged {
+            let index = repo.index()?;
+            for path in changed.keys() {
+                if let Some(entry) = index.get_path(path, 0) {
+                    staged.insert(path.clone(), repo.find_blob(entry.id)?.content().to_vec());
+                }
+            }
+        }
+        let relative = |path: &Path| Some(Path::new(".").join(path.strip_prefix(prefix).ok()?));
+        Ok(Changes {
+            files: changed
+                .into_iter()
+                .filter_map(|(path, lines)| Some((relative(&path)?, lines)))
+                .collect(),
+            staged: staged
+                .into_iter()
+                .filter_map(|(path, contents)| Some((relative(&path)?, contents)))
+                .collect(),
+        })
+    }
+
+    pub fn contains_file(&self, path: &Path) -> bool {
+        self.files.contains_key(path)
+    }
+
+    // whether any changed file is under `dir`
+    pub fn contains_dir(&self, dir: &Path) -> bool {
+        self.files.keys().any(|f| f.starts_with(dir))
+    }
+
+    // what's staged for a file, only with Since::Staged
+    pub fn staged(&self, path: &Path) -> Option<&[u8]> {
+        self.staged.get(path).map(Vec::as_slice)
+    }
+
+    // line is 1-indexed
+    pub fn is_added(&self, path: &Path, line: usize) -> bool {
+        self.files.get(path).is_some_and(|lines| lines.contains(&line))
+    }
+}
+
+// only walks into changed files and the directories containing them
+pub struct ChangedOnly<'a, F> {
+    pub filter: &'a F,
+    pub changes: &'a Changes,
+}
+
+impl<F: PathFilter> PathFilter for ChangedOnly<'_, F> {
+    fn filter_file(&self, p: &Path) -> bool {
+        self.changes.contains_file(p) && self.filter.filter_file(p)
+    }
+    fn filter_dir(&self, p: &Path) -> bool {
+        self.changes.contains_dir(p) && self.filter.filter_dir(p)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::Signature;
+
+    fn commit_all(repo: &Repository) {
+        let mut index = repo.index().unwrap();
+        index.add_all(["*"], git2::IndexAddOption::DEFAULT, None).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = Signature::now("Ann", "ann@example.com").unwrap();
+        let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+        let parents: Vec<_> = parent.iter().collect();
+        repo.commit(Some("HEAD"), &sig, &sig, "commit", &tree, &parents).unwrap();
+    }
+
+    fn stage(repo: &Repository, path: &str) {
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new(path)).unwrap();
+        index.write().unwrap();
+    }
+
+    #[test]
+    fn added_lines_since_a_rev_and_staged() {
+        let dir = std::env::temp_dir().join(format!("todo-rs-changes-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let repo = Repository::init(&dir).unwrap();
+        std::fs::write(dir.join("a.rs"), "fn a() {}\n").unwrap();
+        std::fs::write(dir.join("old.rs"), "// TODO\n").unwrap();
+        commit_all(&repo);
+
+        // staged: a TODO above a(), then not staged: one below it
+        std::fs::write(dir.join("a.rs"), "// TODO: staged\nfn a() {}\n").unwrap();
+        stage(&repo, "a.rs");
+        std::fs::write(dir.join("a.rs"), "// TODO: staged\nfn a() {}\n// TODO: not staged\n").unwrap();
+        std::fs::write(dir.join("new.rs"), "// TODO\n").unwrap();
+
+        let a = Path::new("./a.rs");
+        let since = Changes::discover_in(&dir, &Since::Rev("HEAD".into())).unwrap();
+        assert!(since.is_added(a, 1) && !since.is_added(a, 2) && since.is_added(a, 3));
+        assert!(since.is_added(Path::new("./new.rs"), 1));
+        assert!(!since.contains_file(Path::new("./old.rs")));
+        assert!(since.contains_dir(Path::new(".")));
+        assert_eq!(since.staged(a), None);
+
+        let staged = Changes::discover_in(&dir, &Since::Staged).unwrap();
+        assert!(staged.is_added(a, 1) && !staged.is_added(a, 2) && !staged.is_added(a, 3));
+        assert!(!staged.contains_file(Path::new("./new.rs")));
+        assert_eq!(staged.staged(a), Some(&b"// TODO: staged\nfn a() {}\n"[..]));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,9 +1,14 @@
 use regex::Regex;
 use serde::Deserialize;
 use std::collections::HashMap;
+use std::fmt;
+use std::ops::Deref;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 
 use crate::filstu::PathFilter;
+use crate::lang::{Language, LANGUAGES};
+use crate::{TDError, LOCAL_CONF_LEAF};
 
 #[derive(Debug)]
 pub struct FilePath(pub PathBuf);
@@ -14,6 +19,10 @@ pub struct Patterns(pub Vec<Regex>);
 pub enum ConfigError {
     #[error(transparent)]
     InvalidRegex(#[from] regex::Error),
+    #[error("Unknown language {0}")]
+    UnknownLanguage(String),
+    #[error("{0} must be set in the config")]
+    Missing(&'static str),
 }
 
 impl TryFrom<Vec<String>> for Patterns {
@@ -48,22 +57,39 @@ pub struct Config {
     pub ignore_file_patterns: Patterns,
     pub search_dir_patterns: Patterns,
     pub ignore_dir_patterns: Patterns,
-    //TODO: find by ext (faster)
+    // finders from the language presets, by file extension
+    pub find_by_ext: HashMap<String, Vec<Regex>>,
     pub find_todos: Vec<(Regex, Regex)>,
     pub human_report: Option<FilePath>,
     pub json_report: Option<FilePath>,
+    // skip paths ignored by .gitignore and .ignore files
+    pub respect_gitignore: bool,
+    // where `todo-rs export` sends TODOs
+    pub export_url: Option<String>,
+    pub export_dir: Option<FilePath>,
+    // append a summary of every full scan to this file, for `todo-rs trend`
+    pub history_file: Option<FilePath>,
 }
 
 impl Config {
     pub fn get_todo_finders<'a>(&'a self, path: &Path) -> Vec<&'a Regex> {
+        self.get_named_finders(path).into_iter().map(|(_, f)| f).collect()
+    }
+
+    // finders named after their language preset or the path regex they're configured for
+    pub fn get_named_finders<'a>(&'a self, path: &Path) -> Vec<(String, &'a Regex)> {
+        let ext = path.extension().and_then(|ext| ext.to_str());
+        let mut finders: Vec<(String, &Regex)> = ext
+            .and_then(|ext| Some((Language::by_extension(ext)?, self.find_by_ext.get(ext)?)))
+            .map(|(lang, fs)| fs.iter().map(|f| (lang.name.to_string(), f)).collect())
+            .unwrap_or_default();
         let path = path
             .to_str()
             .map(String::from)
             .unwrap_or(path.to_string_lossy().into_owned());
-        let mut finders = vec![];
         for (check_path, finder) in &self.find_todos {
             if check_path.is_match(&path) {
-                finders.push(finder);
+                finders.push((check_path.to_string(), finder));
             }
         }
         finders
@@ -95,17 +121,58 @@ impl PathFilter for Config {
     }
 }
 
-#[derive(Deserialize)]
+// every field is optional so configs can be layered, see ConfigRaw::merge
+#[derive(Deserialize, Default, Clone, Debug)]
+#[serde(default)]
 pub struct ConfigRaw {
-    pub read_by_default: bool,
-    pub search_by_default: bool,
+    pub read_by_default: Option<bool>,
+    pub search_by_default: Option<bool>,
     pub read_file_patterns: Vec<String>,
     pub ignore_file_patterns: Vec<String>,
     pub search_dir_patterns: Vec<String>,
     pub ignore_dir_patterns: Vec<String>,
     pub find_todos: HashMap<String, String>,
+    // built-in comment syntax presets, "all" for every known language
+    pub languages: Vec<String>,
     pub human_report: Option<String>,
     pub json_report: Option<String>,
+    pub respect_gitignore: Option<bool>,
+    pub export_url: Option<String>,
+    pub export_dir: Option<String>,
+    pub history_file: Option<String>,
+}
+
+impl ConfigRaw {
+    pub fn load(path: &Path) -> Result<Self, TDError> {
+        let text = std::fs::read_to_string(path)?;
+        Ok(toml::from_str(&text)?)
+    }
+
+    // `over` takes precedence: its settings replace ours, patterns are added
+    // to ours and its finders replace ours for the same path regex
+    pub fn merge(mut self, over: ConfigRaw) -> ConfigRaw {
+        self.read_file_patterns.extend(over.read_file_patterns);
+        self.ignore_file_patterns.extend(over.ignore_file_patterns);
+        self.search_dir_patterns.extend(over.search_dir_patterns);
+        self.ignore_dir_patterns.extend(over.ignore_dir_patterns);
+        self.find_todos.extend(over.find_todos);
+        for lang in over.languages {
+            if !self.languages.contains(&lang) {
+                self.languages.push(lang);
+            }
+        }
+        ConfigRaw {
+            read_by_default: over.read_by_default.or(self.read_by_default),
+            search_by_default: over.search_by_default.or(self.search_by_default),
+            human_report: over.human_report.or(self.human_report),
+            json_report: over.json_report.or(self.json_report),
+            respect_gitignore: over.respect_gitignore.or(self.respect_gitignore),
+            export_url: over.export_url.or(self.export_url),
+            export_dir: over.export_dir.or(self.export_dir),
+            history_file: over.history_file.or(self.history_file),
+            ..self
+        }
+    }
 }
 
 impl TryFrom<ConfigRaw> for Config {
@@ -116,9 +183,28 @@ impl TryFrom<ConfigRaw> for Config {
             .into_iter()
             .map(|(path, comnt)| Ok::<_, regex::Error>((Regex::new(&path)?, Regex::new(&comnt)?)))
             .collect::<Result<_, _>>()?;
+        let languages: Vec<&Language> = match value.languages.iter().any(|l| l == "all") {
+            true => LANGUAGES.iter().collect(),
+            false => value
+                .languages
+                .iter()
+                .map(|l| Language::find(l).ok_or(ConfigError::UnknownLanguage(l.clone())))
+                .collect::<Result<_, _>>()?,
+        };
+        let mut find_by_ext = HashMap::new();
+        for lang in languages {
+            let finders = lang.finders();
+            for ext in lang.extensions {
+                find_by_ext.insert(ext.to_string(), finders.clone());
+            }
+        }
         Ok(Config {
-            read_by_default: value.read_by_default,
-            search_by_default: value.search_by_default,
+            find_by_ext,
+            // required, but only in one of the merged files
+            read_by_default: value.read_by_default.ok_or(ConfigError::Missing("read_by_default"))?,
+            search_by_default: value
+                .search_by_default
+                .ok_or(ConfigError::Missing("search_by_default"))?,
             find_todos: finds,
             read_file_patterns: value.read_file_patterns.try_into()?,
             ignore_file_patterns: value.ignore_file_patterns.try_into()?,
@@ -126,6 +212,289 @@ impl TryFrom<ConfigRaw> for Config {
             ignore_dir_patterns: value.ignore_dir_patterns.try_into()?,
             human_report: value.human_report.map(FilePath::from),
             json_report: value.json_report.map(FilePath::from),
+            respect_gitignore: value.respect_gitignore.unwrap_or(false),
+            export_url: value.export_url,
+            export_dir: value.export_dir.map(FilePath::from),
+            history_file: value.history_file.map(FilePath::from),
+        })
+    }
+}
+
+// a config and the files it was merged from, outermost first
+#[derive(Debug)]
+pub struct Layer {
+    pub raw: ConfigRaw,
+    pub cfg: Config,
+    pub sources: Vec<PathBuf>,
+}
+
+impl Layer {
+    fn new(sources: Vec<PathBuf>, raw: ConfigRaw) -> Result<Self, TDError> {
+        Ok(Layer {
+            cfg: raw.clone().try_into()?,
+            raw,
+            sources,
+        })
+    }
+
+    // this layer with the .todo.toml in `dir` on top, if there is one
+    fn nested(&self, dir: &Path) -> Result<Option<Self>, TDError> {
+        let path = dir.join(LOCAL_CONF_LEAF);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let raw = self.raw.clone().merge(ConfigRaw::load(&path)?);
+        let mut sources = self.sources.clone();
+        sources.push(path);
+        Layer::new(sources, raw).map(Some)
+    }
+}
+
+// the global config, every .todo.toml from the repo root down to the scanned
+// directory and, lazily, the .todo.toml of every directory found while walking
+#[derive(Debug)]
+pub struct LayeredConfig {
+    root_dir: PathBuf,
+    root: Arc<Layer>,
+    dirs: Mutex<HashMap<PathBuf, Arc<Layer>>>,
+    // the first nested config that failed to load while filtering paths
+    error: Mutex<Option<TDError>>,
+}
+
+impl LayeredConfig {
+    pub fn new(root_dir: impl Into<PathBuf>, sources: Vec<PathBuf>) -> Result<Self, TDError> {
+        let raw = sources
+            .iter()
+            .try_fold(ConfigRaw::default(), |raw, path| {
+                Ok::<_, TDError>(raw.merge(ConfigRaw::load(path)?))
+            })?;
+        Ok(LayeredConfig {
+            root_dir: root_dir.into(),
+            root: Arc::new(Layer::new(sources, raw)?),
+            dirs: Mutex::new(HashMap::new()),
+            error: Mutex::new(None),
+        })
+    }
+
+    // config that applies to the contents of `dir`
+    pub fn for_dir(&self, dir: &Path) -> Result<Arc<Layer>, TDError> {
+        if dir == self.root_dir || !dir.starts_with(&self.root_dir) {
+            return Ok(self.root.clone());
+        }
+        if let Some(layer) = self.dirs.lock().unwrap().get(dir) {
+            return Ok(layer.clone());
+        }
+        let parent = self.for_dir(dir.parent().unwrap_or(&self.root_dir))?;
+        let layer = match parent.nested(dir) {
+            Ok(Some(layer)) => Arc::new(layer),
+            Ok(None) => parent,
+            Err(e) => return Err(TDError::InFile(dir.join(LOCAL_CONF_LEAF), Arc::new(e))),
+        };
+        self.dirs
+            .lock()
+            .unwrap()
+            .insert(dir.to_path_buf(), layer.clone());
+        Ok(layer)
+    }
+
+    // config that applies to a file or directory
+    pub fn for_path(&self, path: &Path) -> Result<Arc<Layer>, TDError> {
+        self.for_dir(path.parent().unwrap_or(&self.root_dir))
+    }
+
+    // a nested config that failed to load while walking, the paths it applies
+    // to were left out
+    pub fn take_error(&self) -> Result<(), TDError> {
+        match self.error.lock().unwrap().take() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    // PathFilter can't fail, keep the error for take_error
+    fn filter_with(&self, path: &Path, filter: impl Fn(&Config) -> bool) -> bool {
+        match self.for_path(path) {
+            Ok(layer) => filter(&layer.cfg),
+            Err(e) => {
+                self.error.lock().unwrap().get_or_insert(e);
+                false
+            }
+        }
+    }
+
+    pub fn explain(&self, path: &Path) -> Result<Explanation, TDError> {
+        // patterns match paths as they're walked, from the scan root
+        let path = match path.is_absolute() {
+            true => {
+                let root = std::path::absolute(&self.root_dir)?;
+                match path.strip_prefix(&root) {
+                    Ok(relative) => self.root_dir.join(relative),
+                    Err(_) => path.to_path_buf(),
+                }
+            }
+            false if path.starts_with(&self.root_dir) => path.to_path_buf(),
+            false => self.root_dir.join(path),
+        };
+        let layer = self.for_path(&path)?;
+        let path_str = path.to_string_lossy();
+        let matching = |p: &Patterns| {
+            p.0.iter()
+                .filter(|r| r.is_match(&path_str))
+                .map(|r| r.to_string())
+                .collect()
+        };
+        let is_dir = path.is_dir();
+        let (include, ignore) = match is_dir {
+            true => (&layer.cfg.search_dir_patterns, &layer.cfg.ignore_dir_patterns),
+            false => (&layer.cfg.read_file_patterns, &layer.cfg.ignore_file_patterns),
+        };
+        let excluded_by = path
+            .ancestors()
+            .skip(1)
+            .take_while(|d| d.starts_with(&self.root_dir) && *d != self.root_dir)
+            .find(|d| !self.filter_dir(d))
+            .map(Path::to_path_buf);
+        let ext = path.extension().and_then(|e| e.to_str()).unwrap_or_default();
+        let presets = layer
+            .cfg
+            .find_by_ext
+            .get(ext)
+            .into_iter()
+            .flatten()
+            .map(|f| (format!("*.{ext}"), f.to_string()));
+        let finders = presets
+            .chain(
+                layer
+                    .cfg
+                    .find_todos
+                    .iter()
+                    .filter(|(p, _)| p.is_match(&path_str))
+                    .map(|(p, f)| (p.to_string(), f.to_string())),
+            )
+            .collect();
+        Ok(Explanation {
+            included: match is_dir {
+                true => self.filter_dir(&path),
+                false => self.filter_file(&path),
+            },
+            by_default: match is_dir {
+                true => layer.cfg.search_by_default,
+                false => layer.cfg.read_by_default,
+            },
+            include_patterns: matching(include),
+            ignore_patterns: matching(ignore),
+            sources: layer.sources.clone(),
+            excluded_by,
+            finders,
+            path,
         })
     }
 }
+
+// the root layer, for settings that apply to the whole scan
+impl Deref for LayeredConfig {
+    type Target = Config;
+    fn deref(&self) -> &Self::Target {
+        &self.root.cfg
+    }
+}
+
+impl PathFilter for LayeredConfig {
+    fn filter_dir(&self, path: &Path) -> bool {
+        self.filter_with(path, |cfg| cfg.filter_dir(path))
+    }
+    fn filter_file(&self, path: &Path) -> bool {
+        self.filter_with(path, |cfg| cfg.filter_file(path))
+    }
+}
+
+pub struct Explanation {
+    pub path: PathBuf,
+    pub sources: Vec<PathBuf>,
+    pub included: bool,
+    pub by_default: bool,
+    pub include_patterns: Vec<String>,
+    pub ignore_patterns: Vec<String>,
+    // a parent directory that is not searched
+    pub excluded_by: Option<PathBuf>,
+    // (path regex or *.ext for presets, todo regex)
+    pub finders: Vec<(String, String)>,
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.path.display())?;
+        writeln!(f, "config files:")?;
+        for source in &self.sources {
+            writeln!(f, "\t{}", source.display())?;
+        }
+        let verdict = match (self.included, &self.excluded_by) {
+            (_, Some(dir)) => format!("no, {} is not searched", dir.display()),
+            (true, None) => "yes".to_string(),
+            (false, None) => "no".to_string(),
+        };
+        writeln!(f, "scanned: {verdict}")?;
+        writeln!(f, "\tby default: {}", self.by_default)?;
+        for p in &self.include_patterns {
+            writeln!(f, "\tincluded by: {p}")?;
+        }
+        for p in &self.ignore_patterns {
+            writeln!(f, "\tignored by: {p}")?;
+        }
+        writeln!(f, "finders:")?;
+        for (path, finder) in &self.finders {
+            writeln!(f, "\t{path} => {finder}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw(toml: &str) -> ConfigRaw {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn merge_prefers_the_inner_config() {
+        let outer = raw(r#"
+            read_by_default = true
+            search_by_default = false
+            json_report = "outer.json"
+            ignore_file_patterns = ["a"]
+            languages = ["rust", "python"]
+            [find_todos]
+            "x" = "outer"
+            "y" = "outer"
+        "#);
+        let inner = raw(r#"
+            read_by_default = false
+            ignore_file_patterns = ["b"]
+            languages = ["python", "c"]
+            [find_todos]
+            "x" = "inner"
+        "#);
+        let merged = outer.merge(inner);
+        assert_eq!(merged.read_by_default, Some(false));
+        // unset in the inner config
+        assert_eq!(merged.search_by_default, Some(false));
+        assert_eq!(merged.json_report.as_deref(), Some("outer.json"));
+        assert_eq!(merged.ignore_file_patterns, ["a", "b"]);
+        assert_eq!(merged.languages, ["rust", "python", "c"]);
+        assert_eq!(merged.find_todos["x"], "inner");
+        assert_eq!(merged.find_todos["y"], "outer");
+    }
+
+    #[test]
+    fn by_default_settings_are_required() {
+        let only_read = raw("read_by_default = true");
+        assert!(matches!(
+            Config::try_from(only_read.clone()),
+            Err(ConfigError::Missing("search_by_default"))
+        ));
+        let cfg = Config::try_from(only_read.merge(raw("search_by_default = false"))).unwrap();
+        assert!(cfg.read_by_default && !cfg.search_by_default);
+    }
+}
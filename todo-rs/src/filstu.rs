@@ -1,7 +1,11 @@
 use std::fs;
 use std::path::{Path, PathBuf};
 
+use ignore::{WalkBuilder, WalkState};
 use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
 
 #[derive(Debug, Clone, Serialize)]
 #[serde(untagged)]
@@ -72,6 +76,46 @@ impl<Ai, Hi> Node<Ai, Hi> {
             Node::List(h, xs) => Some((h, xs)),
         }
     }
+    pub fn atoms(&self) -> Vec<&Ai> {
+        match self {
+            Node::Atom(a) => vec![a],
+            Node::List(_, xs) => xs.iter().flat_map(Node::atoms).collect(),
+        }
+    }
+
+    // map every atom on `jobs` threads, keeping the tree's shape and order
+    pub fn par_map_atoms<Ao, F>(self, jobs: usize, atom_fn: F) -> Node<Ao, Hi>
+    where
+        Ai: Sync,
+        Ao: Send,
+        F: Fn(&Ai) -> Ao + Sync,
+    {
+        let atoms = self.atoms();
+        let next = AtomicUsize::new(0);
+        let mut mapped: Vec<Option<Ao>> = atoms.iter().map(|_| None).collect();
+        std::thread::scope(|s| {
+            let workers: Vec<_> = (0..jobs.max(1))
+                .map(|_| {
+                    s.spawn(|| {
+                        let mut done = vec![];
+                        loop {
+                            let i = next.fetch_add(1, Ordering::Relaxed);
+                            let Some(atom) = atoms.get(i) else { break done };
+                            done.push((i, atom_fn(atom)));
+                        }
+                    })
+                })
+                .collect();
+            for worker in workers {
+                for (i, out) in worker.join().expect("worker thread panicked") {
+                    mapped[i] = Some(out);
+                }
+            }
+        });
+        let mut mapped = mapped.into_iter().flatten();
+        self.map(|h, _| h, |_| mapped.next().expect("every atom was mapped"))
+    }
+
     pub fn into_atoms(self) -> Vec<Ai> {
         let mut out = vec![];
         self.map(|_, _|(), |a|{
@@ -83,35 +127,88 @@ impl<Ai, Hi> Node<Ai, Hi> {
 
 pub fn read_dir_filtered<F, P>(dir: P, filter: &F) -> Result<Entry, std::io::Error>
 where
-    F: PathFilter,
+    F: PathFilter + Sync,
+    P: AsRef<Path>,
+{
+    walk(dir.as_ref(), filter, false)
+}
+
+// same as read_dir_filtered, but also skips whatever git ignores: .gitignore
+// and .ignore files (in the walked directories or their parents), the repo's
+// .git/info/exclude and the global excludes file
+pub fn read_dir_filtered_ignoring<F, P>(dir: P, filter: &F) -> Result<Entry, std::io::Error>
+where
+    F: PathFilter + Sync,
     P: AsRef<Path>,
 {
-    let dir = dir.as_ref();
+    walk(dir.as_ref(), filter, true)
+}
+
+// directories are read on several threads, the tree is put together after
+fn walk<F: PathFilter + Sync>(dir: &Path, filter: &F, ignoring: bool) -> Result<Entry, std::io::Error> {
     if !dir.is_dir() {
         if filter.filter_dir(dir) {
             return Ok(Entry::Atom(FPath(dir.into())));
         } else {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                "Can't read_dir_filtered a file",
-            ));
+            return Err(std::io::Error::other("Can't read_dir_filtered a file"));
         }
     }
-    let out: Result<Vec<_>, _> = fs::read_dir(dir)?
-        .filter(|f| {
-            f.as_ref()
-                .map(|x| {
-                    let path = x.path();
-                    match path.is_dir() {
-                        true => filter.filter_dir(path.as_path()),
-                        false => filter.filter_file(path.as_path()),
-                    }
-                })
-                .unwrap_or(false)
+    // only the walked directory has to be readable, unreadable ones below are
+    // reported and skipped
+    fs::read_dir(dir)?;
+    let walker = WalkBuilder::new(dir)
+        .standard_filters(ignoring)
+        .hidden(false)
+        .follow_links(true)
+        .build_parallel();
+    let found = Mutex::new(vec![]);
+    walker.run(|| {
+        Box::new(|entry| {
+            // the scan goes on without what can't be read, but says so
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    eprintln!("{e}");
+                    return WalkState::Continue;
+                }
+            };
+            if entry.depth() == 0 {
+                return WalkState::Continue;
+            }
+            let path = entry.path();
+            let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+            let included = match is_dir {
+                true => filter.filter_dir(path) && !(ignoring && path.ends_with(".git")),
+                false => filter.filter_file(path),
+            };
+            if !included {
+                return WalkState::Skip;
+            }
+            found.lock().unwrap().push((path.to_owned(), is_dir));
+            WalkState::Continue
+        })
+    });
+
+    let mut children: HashMap<PathBuf, Vec<(PathBuf, bool)>> = HashMap::new();
+    for (path, is_dir) in found.into_inner().unwrap() {
+        let parent = path.parent().unwrap_or(dir).to_owned();
+        children.entry(parent).or_default().push((path, is_dir));
+    }
+    Ok(tree(dir.to_owned(), &mut children))
+}
+
+// the walk's order depends on the threads, entries are sorted by name
+fn tree(dir: PathBuf, children: &mut HashMap<PathBuf, Vec<(PathBuf, bool)>>) -> Entry {
+    let mut entries = children.remove(&dir).unwrap_or_default();
+    entries.sort();
+    let out = entries
+        .into_iter()
+        .map(|(path, is_dir)| match is_dir {
+            true => tree(path, children),
+            false => Entry::Atom(FPath(path)),
         })
-        .map(|f| read_dir_filtered(f?.path(), filter))
         .collect();
-    Ok(Entry::List(DPath(dir.to_owned()), out?))
+    Entry::List(DPath(dir), out)
 }
 
 pub trait PathFilter {
@@ -179,3 +276,61 @@ mod display {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct All;
+    impl PathFilter for All {
+        fn filter_file(&self, _: &Path) -> bool {
+            true
+        }
+        fn filter_dir(&self, _: &Path) -> bool {
+            true
+        }
+    }
+
+    fn names(tree: &Entry, root: &Path) -> Vec<String> {
+        let mut names: Vec<_> = tree
+            .atoms()
+            .into_iter()
+            .map(|FPath(p)| p.strip_prefix(root).unwrap().to_string_lossy().replace('\\', "/"))
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn walks_what_git_doesnt_ignore() {
+        let root = std::env::temp_dir().join(format!("todo-rs-walk-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        for dir in [".git/info", "src/gen", "target", "docs"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        let files = [
+            (".gitignore", "target/\n*.log\n!keep.log\n"),
+            (".git/info/exclude", "scratch.rs\n"),
+            ("src/.ignore", "gen/\n"),
+            ("src/a.rs", ""),
+            ("src/gen/b.rs", ""),
+            ("target/c.rs", ""),
+            ("docs/d.log", ""),
+            ("docs/keep.log", ""),
+            ("scratch.rs", ""),
+        ];
+        for (path, content) in files {
+            fs::write(root.join(path), content).unwrap();
+        }
+
+        let tree = read_dir_filtered_ignoring(&root, &All).unwrap();
+        assert_eq!(names(&tree, &root), [".gitignore", "docs/keep.log", "src/.ignore", "src/a.rs"]);
+        // the ignore files above the walked directory apply too
+        let tree = read_dir_filtered_ignoring(root.join("docs"), &All).unwrap();
+        assert_eq!(names(&tree, &root), ["docs/keep.log"]);
+
+        let tree = read_dir_filtered(&root, &All).unwrap();
+        assert_eq!(names(&tree, &root).len(), files.len());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
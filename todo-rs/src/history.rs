@@ -0,0 +1,117 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::Write;
+use std::path::Path;
+
+use crate::meta;
+use crate::out::StatsReport;
+use crate::TDError;
+
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+// the stats of one run, the history file has one per line
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Entry {
+    // unix timestamp
+    pub time: i64,
+    #[serde(flatten)]
+    pub stats: StatsReport,
+}
+
+pub fn append(path: &Path, stats: StatsReport) -> Result<(), TDError> {
+    let entry = Entry {
+        time: meta::now(),
+        stats,
+    };
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+    Ok(())
+}
+
+// a missing file is an empty history
+pub fn load(path: &Path) -> Result<Vec<Entry>, TDError> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e.into()),
+    };
+    text.lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| Ok(serde_json::from_str(l)?))
+        .collect()
+}
+
+pub struct Trend<'a>(pub &'a [Entry]);
+pub struct Sparkline<'a>(pub &'a [Entry]);
+
+impl fmt::Display for Trend<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "date        total  change")?;
+        let mut last = None;
+        for entry in self.0 {
+            let total = entry.stats.total;
+            let change = match last {
+                Some(last) => format!("{:+}", total as i64 - last as i64),
+                None => String::new(),
+            };
+            writeln!(f, "{}  {total:>5}  {change:>6}", meta::date(entry.time))?;
+            last = Some(total);
+        }
+        if !self.0.is_empty() {
+            writeln!(f, "\n{}", Sparkline(self.0))?;
+        }
+        Ok(())
+    }
+}
+
+// totals scaled between the smallest and the largest one
+impl fmt::Display for Sparkline<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let totals = self.0.iter().map(|e| e.stats.total);
+        let (Some(min), Some(max)) = (totals.clone().min(), totals.clone().max()) else {
+            return Ok(());
+        };
+        for total in totals {
+            let level = match max - min {
+                0 => 0,
+                range => (total - min) * (SPARKS.len() - 1) / range,
+            };
+            write!(f, "{}", SPARKS[level])?;
+        }
+        write!(f, " {min}..{max}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(time: i64, total: usize) -> Entry {
+        Entry {
+            time,
+            stats: StatsReport {
+                total,
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn sparkline_scales_totals() {
+        let entries = [entry(0, 10), entry(1, 17), entry(2, 3), entry(3, 3)];
+        assert_eq!(Sparkline(&entries).to_string(), "▄█▁▁ 3..17");
+        assert_eq!(Sparkline(&entries[..1]).to_string(), "▁ 10..10");
+        assert_eq!(Sparkline(&[]).to_string(), "");
+    }
+
+    #[test]
+    fn entries_round_trip() {
+        let mut e = entry(1_700_000_000, 4);
+        e.stats.by_owner.insert("alice".into(), 4);
+        let line = serde_json::to_string(&e).unwrap();
+        assert_eq!(serde_json::from_str::<Entry>(&line).unwrap(), e);
+    }
+}
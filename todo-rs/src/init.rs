@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+
+use crate::lang::Language;
+use crate::TDError;
+
+// directories with generated files, dependencies or tool state
+const BUILD_DIRS: &[&str] = &[
+    ".git",
+    "target",
+    "node_modules",
+    "dist",
+    "build",
+    "out",
+    ".venv",
+    "venv",
+    "__pycache__",
+    "vendor",
+    ".direnv",
+    "result",
+    "zig-cache",
+    "_build",
+];
+
+#[derive(Debug, Default)]
+pub struct Detected {
+    // most used first
+    pub languages: Vec<&'static str>,
+    pub build_dirs: Vec<&'static str>,
+}
+
+// languages (by file extension) and build directories found under `dir`,
+// symlinks to directories and unreadable directories below it are skipped
+pub fn detect(dir: &Path) -> Result<Detected, TDError> {
+    let mut files: HashMap<&'static str, usize> = HashMap::new();
+    let mut build_dirs = vec![];
+    detect_dir(dir, &mut files, &mut build_dirs)?;
+    let mut languages: Vec<_> = files.into_iter().collect();
+    languages.sort_by(|(a, an), (b, bn)| bn.cmp(an).then(a.cmp(b)));
+    build_dirs.sort();
+    build_dirs.dedup();
+    Ok(Detected {
+        languages: languages.into_iter().map(|(l, _)| l).collect(),
+        build_dirs,
+    })
+}
+
+fn detect_dir(
+    dir: &Path,
+    files: &mut HashMap<&'static str, usize>,
+    build_dirs: &mut Vec<&'static str>,
+) -> Result<(), TDError> {
+    for entry in std::fs::read_dir(dir)?.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        // not followed, so symlink loops can't recurse forever
+        let Ok(file_type) = entry.file_type() else { continue };
+        if file_type.is_dir() {
+            match BUILD_DIRS.iter().find(|d| **d == name) {
+                Some(build) => build_dirs.push(build),
+                None if name.starts_with('.') => (),
+                None => {
+                    let _ = detect_dir(&path, files, build_dirs);
+                }
+            }
+        } else if let Some(lang) = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .and_then(Language::by_extension)
+        {
+            *files.entry(lang.name).or_default() += 1;
+        }
+    }
+    Ok(())
+}
+
+// the whole config, commented, so it can be edited later
+pub fn starter_config(detected: &Detected) -> String {
+    let quoted = |xs: &mut dyn Iterator<Item = String>| {
+        xs.map(|x| format!("{x:?}")).collect::<Vec<_>>().join(", ")
+    };
+    let ignore_dirs = quoted(
+        &mut detected
+            .build_dirs
+            .iter()
+            .map(|d| format!("(^|/){}$", regex::escape(d))),
+    );
+    let languages = quoted(&mut detected.languages.iter().map(|l| l.to_string()));
+
+    format!(
+        r#"# todo-rs config
+# `todo-rs config --explain <path>` shows what applies to a file
+
+# read every file and search every directory not ignored below
+read_by_default = true
+search_by_default = true
+
+# regexes matched against file and directory paths
+read_file_patterns = []
+ignore_file_patterns = []
+search_dir_patterns = []
+ignore_dir_patterns = [{ignore_dirs}]
+
+# skip whatever .gitignore and .ignore files ignore
+respect_gitignore = true
+
+# built-in comment syntax, looked up by file extension
+# use ["all"] for every known language
+languages = [{languages}]
+
+# also write the text and JSON reports to these files
+# human_report = "todos.txt"
+# json_report = "todos.json"
+
+# append TODO counts to this file on every scan, see `todo-rs trend`
+# history_file = ".todo-history.jsonl"
+
+# extra finders, a path regex and the regex that finds TODOs in those files
+[find_todos]
+# '\.txt$' = 'TODO.*'
+"#
+    )
+}
+
+// config for every project: all languages and every known build directory
+pub fn global_config() -> String {
+    starter_config(&Detected {
+        languages: vec!["all"],
+        build_dirs: BUILD_DIRS.to_vec(),
+    })
+}
+
+// fails if there already is a config at `path`
+pub fn write_config(path: &Path, content: &str) -> Result<(), TDError> {
+    if path.exists() {
+        return Err(TDError::ConfigExists(path.to_path_buf()));
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = std::fs::File::create_new(path)?;
+    file.write_all(content.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conf::{Config, ConfigRaw};
+
+    #[test]
+    fn starter_config_deserializes() {
+        let detected = Detected {
+            languages: vec!["rust", "python"],
+            build_dirs: vec!["target", ".venv"],
+        };
+        for text in [starter_config(&detected), global_config()] {
+            let raw: ConfigRaw = toml::from_str(&text).unwrap();
+            let cfg: Config = raw.try_into().unwrap();
+            assert!(cfg.ignore_dir_patterns.matches_any("./target"));
+            assert!(!cfg.ignore_dir_patterns.matches_any("./src/targets"));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn detect_skips_symlinked_dirs() {
+        let dir = std::env::temp_dir().join(format!("todo-rs-detect-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("src/target")).unwrap();
+        std::fs::write(dir.join("src/main.rs"), "").unwrap();
+        std::fs::write(dir.join("src/lib.rs"), "").unwrap();
+        std::fs::write(dir.join("run.py"), "").unwrap();
+        std::os::unix::fs::symlink(&dir, dir.join("src/loop")).unwrap();
+
+        let detected = detect(&dir).unwrap();
+        assert_eq!(detected.languages, ["rust", "python"]);
+        assert_eq!(detected.build_dirs, ["target"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
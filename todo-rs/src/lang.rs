@@ -0,0 +1,144 @@
+use regex::{escape, Regex};
+
+// tags found by the language presets
+const TAGS: &str = "TODO|FIXME|XXX|HACK|BUG";
+
+// comment syntax of a language, so TODOs are only searched inside comments
+pub struct Language {
+    pub name: &'static str,
+    pub extensions: &'static [&'static str],
+    // markers that start a comment up to the end of the line (doc comments
+    // like `///` or `//!` are covered by their shorter marker)
+    pub line: &'static [&'static str],
+    // (open, close) pairs of block comments
+    pub block: &'static [(&'static str, &'static str)],
+}
+
+const C_BLOCK: &[(&str, &str)] = &[("/*", "*/")];
+
+pub const LANGUAGES: &[Language] = &[
+    Language { name: "rust", extensions: &["rs"], line: &["//"], block: C_BLOCK },
+    Language { name: "c", extensions: &["c", "h"], line: &["//"], block: C_BLOCK },
+    Language {
+        name: "cpp",
+        extensions: &["cpp", "cc", "cxx", "hpp", "hh", "hxx"],
+        line: &["//"],
+        block: C_BLOCK,
+    },
+    Language { name: "csharp", extensions: &["cs"], line: &["//"], block: C_BLOCK },
+    Language { name: "go", extensions: &["go"], line: &["//"], block: C_BLOCK },
+    Language { name: "java", extensions: &["java"], line: &["//"], block: C_BLOCK },
+    Language { name: "kotlin", extensions: &["kt", "kts"], line: &["//"], block: C_BLOCK },
+    Language { name: "swift", extensions: &["swift"], line: &["//"], block: C_BLOCK },
+    Language { name: "zig", extensions: &["zig"], line: &["//"], block: &[] },
+    Language {
+        name: "javascript",
+        extensions: &["js", "mjs", "cjs", "jsx"],
+        line: &["//"],
+        block: C_BLOCK,
+    },
+    Language { name: "typescript", extensions: &["ts", "tsx"], line: &["//"], block: C_BLOCK },
+    Language { name: "css", extensions: &["css", "scss"], line: &[], block: C_BLOCK },
+    Language { name: "php", extensions: &["php"], line: &["//", "#"], block: C_BLOCK },
+    Language {
+        name: "python",
+        extensions: &["py", "pyi"],
+        line: &["#"],
+        block: &[("\"\"\"", "\"\"\""), ("'''", "'''")],
+    },
+    Language { name: "ruby", extensions: &["rb"], line: &["#"], block: &[("=begin", "=end")] },
+    Language { name: "shell", extensions: &["sh", "bash", "zsh", "fish"], line: &["#"], block: &[] },
+    Language { name: "elixir", extensions: &["ex", "exs"], line: &["#"], block: &[] },
+    Language { name: "nix", extensions: &["nix"], line: &["#"], block: C_BLOCK },
+    Language { name: "toml", extensions: &["toml"], line: &["#"], block: &[] },
+    Language { name: "yaml", extensions: &["yml", "yaml"], line: &["#"], block: &[] },
+    Language { name: "lua", extensions: &["lua"], line: &["--"], block: &[("--[[", "]]")] },
+    Language { name: "sql", extensions: &["sql"], line: &["--"], block: C_BLOCK },
+    Language { name: "haskell", extensions: &["hs"], line: &["--"], block: &[("{-", "-}")] },
+    Language {
+        name: "html",
+        extensions: &["html", "htm", "xml", "svg", "md"],
+        line: &[],
+        block: &[("<!--", "-->")],
+    },
+    Language {
+        name: "lisp",
+        extensions: &["lisp", "lsp", "el", "scm", "clj"],
+        line: &[";"],
+        block: &[],
+    },
+    Language { name: "latex", extensions: &["tex", "sty"], line: &["%"], block: &[] },
+    Language { name: "vim", extensions: &["vim"], line: &["\""], block: &[] },
+];
+
+impl Language {
+    pub fn find(name: &str) -> Option<&'static Language> {
+        LANGUAGES.iter().find(|l| l.name == name)
+    }
+
+    pub fn by_extension(ext: &str) -> Option<&'static Language> {
+        LANGUAGES.iter().find(|l| l.extensions.contains(&ext))
+    }
+
+    // one regex per comment marker, the TODO itself is the `todo` group
+    pub fn finders(&self) -> Vec<Regex> {
+        let todo = format!(r"(?P<todo>(?:{TAGS})\b[^\n]*?)");
+        let line = self
+            .line
+            .iter()
+            .map(|m| format!(r"{}[^\w\n]*?{todo}[^\S\n]*$", escape(m)));
+        let block = self.block.iter().map(|(open, close)| {
+            format!(
+                r"{}[^\w\n]*?{todo}[^\S\n]*(?:{}|$)",
+                escape(open),
+                escape(close)
+            )
+        });
+        // lines inside a /* */ block usually start with a *
+        let block_lines = self
+            .block
+            .iter()
+            .filter(|(open, _)| *open == "/*")
+            .map(|_| format!(r"^[^\S\n]*\*[^\w\n/]*?{todo}[^\S\n]*(?:\*/|$)"));
+        line.chain(block)
+            .chain(block_lines)
+            .map(|r| Regex::new(&format!("(?m){r}")).expect("language presets are valid regexes"))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn todos(lang: &str, text: &str) -> Vec<String> {
+        let mut found: Vec<_> = Language::find(lang)
+            .unwrap()
+            .finders()
+            .iter()
+            .flat_map(|r| {
+                r.captures_iter(text)
+                    .map(|c| (c["todo"].to_string(), c.name("todo").unwrap().start()))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        found.sort_by_key(|(_, pos)| *pos);
+        found.into_iter().map(|(t, _)| t).collect()
+    }
+
+    #[test]
+    fn rust_comments() {
+        let src = "/// TODO(a): doc\nlet todo = 1; // FIXME: fix\n/* HACK: block */\n * XXX: inner\n";
+        assert_eq!(
+            todos("rust", src),
+            ["TODO(a): doc", "FIXME: fix", "HACK: block", "XXX: inner"]
+        );
+        assert!(todos("rust", "let TODO = 1;").is_empty());
+    }
+
+    #[test]
+    fn python_comments() {
+        let src = "x = 1  # TODO: later\n\"\"\"FIXME: docstring\"\"\"\n";
+        assert_eq!(todos("python", src), ["TODO: later", "FIXME: docstring"]);
+    }
+}
@@ -1,9 +1,17 @@
-use std::collections::HashMap;
 use std::path::PathBuf;
 
+pub mod args;
+pub mod baseline;
+pub mod blame;
+pub mod changes;
 pub mod conf;
 pub mod filstu;
+pub mod history;
+pub mod init;
+pub mod lang;
+pub mod meta;
 pub mod out;
+pub mod tui;
 
 const CONF_EXT: &str = "toml";
 const CONF_NAME: &str = "config";
@@ -19,24 +27,79 @@ pub enum TDError {
     TomlDeError(#[from] toml::de::Error),
     #[error(transparent)]
     IOError(#[from] std::io::Error),
-    #[error("Config file not found")]
+    #[error("Config file not found, run `todo-rs init` to create one")]
     ConfigNotFound,
+    #[error("Config file {0} set by TODORS_CONFIG not found")]
+    EnvConfigNotFound(PathBuf),
+    #[error("Config file {0} already exists")]
+    ConfigExists(PathBuf),
     #[error("Can't start JSON report from file, only from a directory")]
     TriedJSONReportFromFile,
     #[error(transparent)]
     JSONSerdeError(#[from] serde_json::Error),
+    #[error(transparent)]
+    GitError(#[from] git2::Error),
+    #[error("Can't blame TODOs in a bare repository")]
+    BareRepository,
+    #[error("File {0} is outside of the repository")]
+    OutsideRepository(PathBuf),
+    #[error("Unknown argument {0}")]
+    UnknownArg(String),
+    #[error("Argument {0} needs a value")]
+    MissingArgValue(String),
+    #[error("Invalid value {1} for argument {0}")]
+    InvalidArgValue(String, String),
+    #[error(transparent)]
+    HttpError(#[from] Box<ureq::Error>),
+    #[error("Nowhere to export to, set export_url or export_dir or pass --url or --dir")]
+    NoExportTarget,
+    #[error("--added-only needs --since REV or --staged")]
+    AddedOnlyWithoutDiff,
+    #[error("No history to show, set history_file in the config")]
+    NoHistoryFile,
+    #[error("{0}: {1}")]
+    InFile(PathBuf, std::sync::Arc<TDError>),
+}
+
+// a single TODO found in a file, line and col are 1-indexed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TodoMatch {
+    pub pos: usize,
+    pub line: usize,
+    pub col: usize,
+    pub text: String,
+    // name of the finder that found it, see Config::get_named_finders
+    pub finder: String,
+    pub context_before: Vec<String>,
+    pub context_after: Vec<String>,
 }
 
-pub fn rev_find_config() -> Option<PathBuf> {
-    let mut cwd = std::env::current_dir().unwrap();
-    loop {
-        let Some(next) = cwd.parent() else { break None };
-        let conf = cwd.clone().join(LOCAL_CONF_LEAF);
-        if conf.exists() {
-            break Some(conf);
+// .todo.toml files from the repo root (or the filesystem root, outside of a
+// repo) down to the current directory
+pub fn find_local_configs() -> Vec<PathBuf> {
+    let cwd = std::env::current_dir().unwrap();
+    let mut found = vec![];
+    for dir in cwd.ancestors() {
+        let conf = dir.join(LOCAL_CONF_LEAF);
+        if conf.is_file() {
+            found.push(conf);
         }
-        cwd = next.to_path_buf();
+        if dir.join(".git").exists() {
+            break;
+        }
+    }
+    found.reverse();
+    found
+}
+
+// every config that applies to the current directory, in the order they're layered.
+// The default global config is optional, one set with TODORS_CONFIG isn't
+pub fn find_configs() -> Result<Vec<PathBuf>, TDError> {
+    let global = find_global_config().filter(|p| p.is_file());
+    if let (None, Some(path)) = (&global, std::env::var_os("TODORS_CONFIG")) {
+        return Err(TDError::EnvConfigNotFound(path.into()));
     }
+    Ok(global.into_iter().chain(find_local_configs()).collect())
 }
 
 pub fn find_global_config() -> Option<PathBuf> {
@@ -48,29 +111,123 @@ pub fn find_global_config() -> Option<PathBuf> {
 }
 
 impl crate::filstu::FPath {
-    pub fn count_todos(&self, cfg: &crate::conf::Config) -> Result<usize, TDError> {
-        let finders = cfg.get_todo_finders(self.0.as_path());
+    pub fn count_todos(&self, cfg: &crate::conf::LayeredConfig) -> Result<usize, TDError> {
+        let layer = cfg.for_path(&self.0)?;
+        let finders = layer.cfg.get_todo_finders(self.0.as_path());
+        if finders.is_empty() {
+            return Ok(0);
+        }
         let cont = std::fs::read_to_string(&self.0)?;
-        Ok(finders
+        let mut starts: Vec<usize> = finders
             .into_iter()
-            .fold(0, |i, f| i + f.find_iter(&cont).count()))
+            .flat_map(|f| f.captures_iter(&cont).map(|c| todo_match(&c).start()))
+            .collect();
+        starts.sort();
+        starts.dedup();
+        Ok(starts.len())
     }
 
     pub fn report_todos(
         &self,
-        cfg: &crate::conf::Config,
-    ) -> Result<HashMap<usize, String>, TDError> {
-        let finders = cfg.get_todo_finders(self.0.as_path());
-        let cont = std::fs::read_to_string(&self.0)?;
-        let mut matches = HashMap::new();
-        for finder in finders {
-            for mt in finder.find_iter(&cont) {
-                let txt = mt.as_str().to_string();
-                let start = mt.start();
-                matches.insert(start, txt);
+        cfg: &crate::conf::LayeredConfig,
+        context: usize,
+    ) -> Result<Vec<TodoMatch>, TDError> {
+        self.report_todos_in(cfg, context, None)
+    }
+
+    // TODOs in `contents` instead of the file on disk, when given
+    pub fn report_todos_in(
+        &self,
+        cfg: &crate::conf::LayeredConfig,
+        context: usize,
+        contents: Option<&[u8]>,
+    ) -> Result<Vec<TodoMatch>, TDError> {
+        let layer = cfg.for_path(&self.0)?;
+        let finders = layer.cfg.get_named_finders(self.0.as_path());
+        // no need to read files no finder applies to
+        if finders.is_empty() {
+            return Ok(vec![]);
+        }
+        let cont = match contents {
+            Some(bytes) => String::from_utf8(bytes.to_vec())
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?,
+            None => std::fs::read_to_string(&self.0)?,
+        };
+        let mut matches = vec![];
+        for (name, finder) in &finders {
+            for caps in finder.captures_iter(&cont) {
+                let mt = todo_match(&caps);
+                matches.push((mt.start(), mt.as_str(), name));
             }
         }
+        // several finders may match at the same place, the first one wins
+        matches.sort_by_key(|(pos, _, _)| *pos);
+        matches.dedup_by_key(|(pos, _, _)| *pos);
+
+        let lines: Vec<&str> = cont.lines().collect();
+        let starts = line_starts(&cont);
+        Ok(matches
+            .into_iter()
+            .map(|(pos, text, finder)| {
+                let (line, col) = line_col(&cont, &starts, pos);
+                let context_before = lines[(line - 1).saturating_sub(context)..line - 1]
+                    .iter()
+                    .map(|l| l.to_string())
+                    .collect();
+                let context_after = lines
+                    .iter()
+                    .skip(line)
+                    .take(context)
+                    .map(|l| l.to_string())
+                    .collect();
+                TodoMatch {
+                    pos,
+                    line,
+                    col,
+                    text: text.to_string(),
+                    finder: finder.clone(),
+                    context_before,
+                    context_after,
+                }
+            })
+            .collect())
+    }
+}
+
+// byte offset of the start of each line
+fn line_starts(cont: &str) -> Vec<usize> {
+    std::iter::once(0)
+        .chain(cont.match_indices('\n').map(|(i, _)| i + 1))
+        .collect()
+}
+
+// 1-indexed line and column (in chars) of a byte offset
+fn line_col(cont: &str, starts: &[usize], pos: usize) -> (usize, usize) {
+    let line = starts.partition_point(|&start| start <= pos);
+    (line, cont[starts[line - 1]..pos].chars().count() + 1)
+}
+
+// finders may put the TODO itself in a `todo` group, otherwise it's the whole match
+fn todo_match<'h>(caps: &regex::Captures<'h>) -> regex::Match<'h> {
+    caps.name("todo")
+        .unwrap_or_else(|| caps.get(0).expect("group 0 is always present"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(cont: &str, needle: &str) -> (usize, usize) {
+        line_col(cont, &line_starts(cont), cont.find(needle).unwrap())
+    }
 
-        Ok(matches)
+    #[test]
+    fn lines_and_columns() {
+        assert_eq!(at("TODO a\nb", "TODO"), (1, 1));
+        assert_eq!(at("x\r\n  TODO\r\ny", "TODO"), (2, 3));
+        assert_eq!(at("a\r\n\r\nTODO", "TODO"), (3, 1));
+        // columns count chars, not bytes
+        assert_eq!(at("// héllo ✓ TODO", "TODO"), (1, 12));
+        assert_eq!(at("a\nb\n    TODO", "TODO"), (3, 5));
     }
 }
@@ -1,52 +1,206 @@
 use todo_rs::*;
-use conf::{Config, ConfigRaw};
-
-use self::filstu::{DPath, FPath, Node};
-use self::out::{JSONReport, TextReport};
-
-fn main() -> Result<(), TDError> {
-    let config_path = rev_find_config()
-        .or(find_global_config())
-        .ok_or(TDError::ConfigNotFound)?;
-    let config_text = std::fs::read_to_string(config_path)?;
-    let cfg: ConfigRaw = toml::from_str(&config_text)?;
-    let cfg: Config = cfg.try_into()?;
-
-    let flst = filstu::read_dir_filtered(".", &cfg)?;
-
-    let args: Vec<_> = std::env::args().skip(1).collect();
-    let xs: Vec<&str> = args.iter().map(|f|f.as_str()).collect();
-    match &xs[..] {
-        ["--tree"] | [] => {
-            display_report(flst, cfg)
+use conf::LayeredConfig;
+
+use self::args::{Args, Command, Output};
+use self::blame::Blamer;
+use self::changes::{ChangedOnly, Changes};
+use self::filstu::{DPath, FPath, Node, PathFilter};
+use self::out::{
+    CSVReport, Export, ExportSink, IntoReport, IssueStubSink, JSONReport, MarkdownReport, Report,
+    ReportOptions, SARIFReport, StatsReport, TextReport, TodoFilter, TreeReport, WebhookSink,
+};
+
+fn main() {
+    if let Err(e) = program() {
+        eprintln!("{e}");
+        std::process::exit(1);
+    }
+}
+
+fn program() -> Result<(), TDError> {
+    let args = Args::parse(std::env::args().skip(1))?;
+    if let Command::Init { global } = args.command {
+        return init_config(global);
+    }
+
+    let configs = find_configs()?;
+    if configs.is_empty() {
+        return Err(TDError::ConfigNotFound);
+    }
+    let cfg = LayeredConfig::new(".", configs)?;
+
+    if let Command::Explain(path) = &args.command {
+        print!("{}", cfg.explain(path)?);
+        return Ok(());
+    }
+    if let Command::Trend { sparkline } = args.command {
+        let conf::FilePath(path) = cfg.history_file.as_ref().ok_or(TDError::NoHistoryFile)?;
+        let entries = history::load(path)?;
+        match sparkline {
+            true => println!("{}", history::Sparkline(&entries)),
+            false => print!("{}", history::Trend(&entries)),
         }
-        ["--json"] => {
-            make_json_report(flst, cfg)
+        return Ok(());
+    }
+    let changes = args.since.as_ref().map(Changes::discover).transpose()?;
+    let ignoring = args.respect_gitignore.unwrap_or(cfg.respect_gitignore);
+    let flst = match &changes {
+        Some(changes) => walk(&ChangedOnly { filter: &cfg, changes }, ignoring)?,
+        None => walk(&cfg, ignoring)?,
+    };
+    cfg.take_error()?;
+
+    if let Command::Browse = args.command {
+        // show some context even if -C wasn't given
+        let context = if args.context == 0 { 3 } else { args.context };
+        return tui::browse(flst, &cfg, context, args.jobs, args.owner);
+    }
+
+    let opts = ReportOptions {
+        blamer: args.blame.then(|| Blamer::discover(".")).transpose()?,
+        older_than: args.older_than,
+        sort: args.sort,
+        context: args.context,
+        filter: TodoFilter {
+            owner: args.owner,
+            overdue_at: args.overdue.then(meta::today),
+            max_priority: args.priority,
+        },
+        jobs: args.jobs,
+        changes,
+        added_only: args.added_only,
+    };
+    if let Command::Export { url, dir } = args.command {
+        return export(flst, &cfg, &opts, url, dir);
+    }
+    // read before the json_report is overwritten, it may be the same file
+    let baseline = args.baseline.map(baseline::load).transpose()?;
+
+    // the config's report files, history and baseline are about every TODO,
+    // only what's printed is filtered
+    let all = out::scan(flst, &cfg, &opts)?;
+    let shown = all.filtered(&opts);
+    match args.output {
+        Output::Tree => print_text::<TreeReport>(shown.clone(), &cfg),
+        Output::Json => print_json::<JSONReport>(shown.clone(), &cfg),
+        Output::Sarif => print_json::<SARIFReport>(shown.clone(), &cfg),
+        Output::Text => print_text::<TextReport>(shown.clone(), &cfg),
+        Output::Markdown => print_text::<MarkdownReport>(shown.clone(), &cfg),
+        Output::Csv => print_text::<CSVReport>(shown.clone(), &cfg),
+        Output::Stats => print_text::<StatsReport>(shown.clone(), &cfg),
+    }?;
+
+    // partial scans would look like the backlog shrank
+    if let (Some(conf::FilePath(path)), None) = (&cfg.history_file, &args.since) {
+        let stats: StatsReport = all.clone().into_report(&cfg)?;
+        history::append(path, stats)?;
+    }
+
+    if let Some(conf::FilePath(path)) = &cfg.human_report {
+        let report: TextReport = all.clone().into_report(&cfg)?;
+        std::fs::write(path, report.to_string())?;
+    }
+    if cfg.json_report.is_some() || baseline.is_some() {
+        let report: JSONReport = all.into_report(&cfg)?;
+        let current = serde_json::to_value(&report)?;
+        if let Some(conf::FilePath(path)) = &cfg.json_report {
+            std::fs::write(path, serde_json::to_string(&current)?)?;
         }
-        ["--text"] => {
-            make_text_report(flst, cfg)
+        if let Some(baseline) = baseline {
+            let new = baseline::new_todos(&baseline, &current);
+            for todo in &new {
+                eprintln!("new TODO: {todo}");
+            }
+            if !new.is_empty() {
+                std::process::exit(1);
+            }
         }
-        _=>{
-            panic!()
+    }
+    // every TODO on an added line is a new one
+    if args.added_only {
+        let report: JSONReport = shown.into_report(&cfg)?;
+        let new = baseline::entries(&serde_json::to_value(&report)?);
+        // the tree only has counts, name them
+        if args.output == Output::Tree {
+            for todo in &new {
+                eprintln!("new TODO: {todo}");
+            }
+        }
+        if !new.is_empty() {
+            std::process::exit(1);
         }
     }
+    Ok(())
 }
 
-fn display_report(flst: Node<FPath, DPath>, cfg: Config)  -> Result<(), TDError>{
-    let tds = out::show_todos(flst, &cfg);
-    println!("{tds}");
+fn walk<F: PathFilter + Sync>(filter: &F, respect_gitignore: bool) -> Result<Node<FPath, DPath>, TDError> {
+    Ok(match respect_gitignore {
+        true => filstu::read_dir_filtered_ignoring(".", filter)?,
+        false => filstu::read_dir_filtered(".", filter)?,
+    })
+}
+
+fn init_config(global: bool) -> Result<(), TDError> {
+    let (path, content) = match global {
+        true => {
+            let path = find_global_config().ok_or(TDError::ConfigNotFound)?;
+            (path, init::global_config())
+        }
+        false => {
+            let detected = init::detect(".".as_ref())?;
+            (LOCAL_CONF_LEAF.into(), init::starter_config(&detected))
+        }
+    };
+    init::write_config(&path, &content)?;
+    println!("Wrote {}", path.display());
+    Ok(())
+}
+
+fn export(
+    flst: Node<FPath, DPath>,
+    cfg: &LayeredConfig,
+    opts: &ReportOptions,
+    url: Option<String>,
+    dir: Option<std::path::PathBuf>,
+) -> Result<(), TDError> {
+    let url = url.or(cfg.export_url.clone());
+    let dir = dir.or(cfg.export_dir.as_ref().map(|conf::FilePath(p)| p.clone()));
+    let mut sinks: Vec<(String, Box<dyn ExportSink>)> = vec![];
+    if let Some(url) = url {
+        sinks.push((url.clone(), Box::new(WebhookSink { url })));
+    }
+    if let Some(dir) = dir {
+        sinks.push((dir.display().to_string(), Box::new(IssueStubSink { dir })));
+    }
+    if sinks.is_empty() {
+        return Err(TDError::NoExportTarget);
+    }
+
+    let Export(items) = out::make_report(flst, cfg, opts)?;
+    for (target, mut sink) in sinks {
+        let summary = sink.export(&items)?;
+        println!("{target}: {summary}");
+    }
     Ok(())
 }
 
-fn make_json_report(flst: Node<FPath, DPath>, cfg: Config) -> Result<(), TDError> {
-    let jsout: JSONReport = out::make_report(flst, &cfg)?;
+fn print_json<R>(report: Report, cfg: &LayeredConfig) -> Result<(), TDError>
+where
+    Report: IntoReport<R, Error = TDError>,
+    R: serde::Serialize,
+{
+    let jsout: R = report.into_report(cfg)?;
     let out = serde_json::to_string(&jsout)?;
     print!("{out}");
     Ok(())
 }
 
-fn make_text_report(flst: Node<FPath, DPath>, cfg: Config) -> Result<(), TDError> {
-    let out: TextReport = out::make_report(flst, &cfg)?;
+fn print_text<R>(report: Report, cfg: &LayeredConfig) -> Result<(), TDError>
+where
+    Report: IntoReport<R, Error = TDError>,
+    R: std::fmt::Display,
+{
+    let out: R = report.into_report(cfg)?;
     print!("{out}");
     Ok(())
 }
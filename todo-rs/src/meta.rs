@@ -0,0 +1,173 @@
+use regex::Regex;
+use serde::Serialize;
+use std::sync::LazyLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// TAG, then (args) or [args] and/or a date, then an optional ':'
+static TODO_META: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?x)
+        \b(?P<tag>[A-Z]{3,})\b
+        (?:\s*\((?P<paren>[^)]*)\))?
+        (?:\s*\[(?P<bracket>[^\]]*)\])?
+        (?:\s+(?P<date>\d{4}-\d{2}-\d{2}))?
+        \s*:?\s*
+        (?P<message>.*)",
+    )
+    .unwrap()
+});
+static DATE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\d{4}-\d{2}-\d{2}$").unwrap());
+static ISSUE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(#\d+|[A-Z][A-Z0-9]*-\d+)$").unwrap());
+static PRIORITY: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^[pP](\d)$").unwrap());
+// a name or @handle, so `TODO (see below)` has no owner
+static OWNER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^@?[A-Za-z_][\w.-]*$").unwrap());
+
+// conventional TODO annotations, like `TODO(alice, p1): ...` or `FIXME[#123] 2026-12-01: ...`
+#[derive(Debug, Default, Serialize, Hash, PartialEq, Eq, Clone)]
+pub struct TodoMeta {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issue: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<u8>,
+    // YYYY-MM-DD, compares correctly as a string
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub due: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+impl TodoMeta {
+    pub fn parse(text: &str) -> Self {
+        let Some(caps) = TODO_META.captures(text) else {
+            return TodoMeta::default();
+        };
+        let mut meta = TodoMeta {
+            tag: caps.name("tag").map(|m| m.as_str().to_string()),
+            due: caps.name("date").map(|m| m.as_str().to_string()),
+            message: caps
+                .name("message")
+                .map(|m| m.as_str().trim().to_string())
+                .filter(|m| !m.is_empty()),
+            ..TodoMeta::default()
+        };
+        let args = ["paren", "bracket"]
+            .into_iter()
+            .filter_map(|g| caps.name(g))
+            .flat_map(|m| m.as_str().split(','))
+            .map(str::trim)
+            .filter(|a| !a.is_empty());
+        for arg in args {
+            if let Some(p) = PRIORITY.captures(arg) {
+                meta.priority = p[1].parse().ok();
+            } else if ISSUE.is_match(arg) {
+                meta.issue = Some(arg.to_string());
+            } else if DATE.is_match(arg) {
+                meta.due = Some(arg.to_string());
+            } else if OWNER.is_match(arg) {
+                meta.owner = Some(arg.trim_start_matches('@').to_string());
+            }
+        }
+        meta
+    }
+
+    pub fn is_overdue(&self, today: &str) -> bool {
+        self.due.as_deref().is_some_and(|due| due < today)
+    }
+}
+
+impl std::fmt::Display for TodoMeta {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut fields = vec![];
+        if let Some(owner) = &self.owner {
+            fields.push(format!("@{owner}"));
+        }
+        if let Some(issue) = &self.issue {
+            fields.push(issue.clone());
+        }
+        if let Some(p) = self.priority {
+            fields.push(format!("p{p}"));
+        }
+        if let Some(due) = &self.due {
+            fields.push(format!("due {due}"));
+        }
+        if !fields.is_empty() {
+            write!(f, "[{}]", fields.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+// today's date as YYYY-MM-DD (UTC)
+pub fn today() -> String {
+    date(now())
+}
+
+pub fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+// YYYY-MM-DD (UTC) of a unix timestamp
+pub fn date(time: i64) -> String {
+    let (y, m, d) = civil_from_days(time.div_euclid(60 * 60 * 24));
+    format!("{y:04}-{m:02}-{d:02}")
+}
+
+// http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + i64::from(m <= 2);
+    (y, m, d)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_conventional_forms() {
+        let m = TodoMeta::parse("TODO(alice): clean up");
+        assert_eq!(m.owner.as_deref(), Some("alice"));
+        assert_eq!(m.message.as_deref(), Some("clean up"));
+
+        let m = TodoMeta::parse("FIXME[#123] broken");
+        assert_eq!(m.tag.as_deref(), Some("FIXME"));
+        assert_eq!(m.issue.as_deref(), Some("#123"));
+
+        let m = TodoMeta::parse("// TODO(p1)");
+        assert_eq!(m.priority, Some(1));
+        assert_eq!(m.owner, None);
+
+        let m = TodoMeta::parse("TODO(@bob.smith, #4)");
+        assert_eq!(m.owner.as_deref(), Some("bob.smith"));
+        for text in ["TODO (see below)", "TODO(1st pass)", "TODO(!)"] {
+            assert_eq!(TodoMeta::parse(text).owner, None, "{text}");
+        }
+
+        let m = TodoMeta::parse("TODO 2026-12-01: ship it");
+        assert_eq!(m.due.as_deref(), Some("2026-12-01"));
+        assert!(m.is_overdue("2027-01-01"));
+        assert!(!m.is_overdue("2026-12-01"));
+    }
+
+    #[test]
+    fn civil_dates() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(19782), (2024, 2, 29));
+    }
+}
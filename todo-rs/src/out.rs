@@ -1,33 +1,117 @@
-use crate::conf::Config;
+use crate::args::SortBy;
+use crate::blame::{Blame, Blamer};
+use crate::changes::Changes;
+use crate::conf::{Config, LayeredConfig};
 use crate::filstu::{DPath, FPath, Node};
+use crate::meta::TodoMeta;
 use crate::TDError;
 use serde::Serialize;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::fmt::{self, Write};
 use colored::Colorize;
 
 #[derive(Debug, Serialize, Hash, PartialEq, Eq, Clone)]
 struct Todo {
     pos: usize,
+    line: usize,
+    col: usize,
     text: String,
+    #[serde(skip)]
+    finder: String,
+    #[serde(flatten)]
+    meta: TodoMeta,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    context_before: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    context_after: Vec<String>,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    blame: Option<Blame>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Clone, Default)]
 struct FileTodos {
     from: PathBuf,
     todos: Vec<Todo>,
+    // the file couldn't be read or blamed
+    #[serde(skip)]
+    error: Option<Arc<TDError>>,
 }
 
-pub struct Report(Node<Result<FileTodos, TDError>, DPath>);
+impl FileTodos {
+    fn ok(self) -> Result<FileTodos, TDError> {
+        match self.error {
+            Some(e) => Err(TDError::InFile(self.from, e)),
+            None => Ok(self),
+        }
+    }
+}
+
+// the scanned files and how their TODOs are ordered across files
+#[derive(Clone)]
+pub struct Report(Node<FileTodos, DPath>, SortBy);
+
+#[derive(Default)]
+pub struct ReportOptions {
+    // attach git blame information to each TODO
+    pub blamer: Option<Blamer>,
+    // only report TODOs last changed more than N days ago
+    pub older_than: Option<i64>,
+    pub sort: SortBy,
+    // lines of source to include around each TODO
+    pub context: usize,
+    pub filter: TodoFilter,
+    // threads used to read and search files
+    pub jobs: usize,
+    // the changes scanned with --since or --staged
+    pub changes: Option<Changes>,
+    // only report TODOs on lines added in `changes`
+    pub added_only: bool,
+}
+
+// filters on the metadata parsed from each TODO
+#[derive(Default)]
+pub struct TodoFilter {
+    pub owner: Option<String>,
+    // only keep TODOs due before this date (YYYY-MM-DD)
+    pub overdue_at: Option<String>,
+    // only keep TODOs with this priority or more urgent (p0 is the most urgent)
+    pub max_priority: Option<u8>,
+}
+
+impl TodoFilter {
+    fn matches(&self, meta: &TodoMeta) -> bool {
+        let owner = match &self.owner {
+            Some(owner) => meta.owner.as_ref() == Some(owner),
+            None => true,
+        };
+        let overdue = match &self.overdue_at {
+            Some(today) => meta.is_overdue(today),
+            None => true,
+        };
+        let priority = match self.max_priority {
+            Some(max) => meta.priority.is_some_and(|p| p <= max),
+            None => true,
+        };
+        owner && overdue && priority
+    }
+}
+
+mod export;
+mod formats;
+mod stats;
+pub use export::{Export, ExportItem, ExportSink, ExportSummary, IssueStubSink, WebhookSink};
+pub use formats::{CSVReport, MarkdownReport, SARIFReport};
+pub use stats::StatsReport;
 
 #[derive(Serialize)]
 pub struct JSONReport(json::JsonR);
-pub struct TextReport(Node<Result<FileTodos, TDError>, DPath>);
+pub struct TextReport(Vec<FileTodos>);
 
 mod json {
     use super::*;
-    type Nd = Node<Result<FileTodos, TDError>, DPath>;
+    type Nd = Node<FileTodos, DPath>;
     #[derive(Debug, Serialize, PartialEq, Eq, Clone)]
     #[serde(untagged)]
     enum D{
@@ -58,7 +142,7 @@ mod json {
     fn node_update_parent(node: Nd, parent: &mut HashMap<JPath, D>) -> Result<(), TDError> {
         match node {
             Node::Atom(f) => {
-                let f = f?;
+                let f = f.ok()?;
                 parent.insert(JPath(f.from), D::File(f.todos));
             }
             Node::List(h, xs) => {
@@ -85,65 +169,116 @@ mod json {
     }
 }
 
-pub fn show_todos(flst: Node<FPath, DPath>, cfg: &Config) -> Node<String, DPath> {
-    flst.map(
-        |d, _| d,
-        |f| {
-            let count = f.count_todos(cfg);
-            let count = match count {
-                Ok(0) => "0".green(),
-                Ok(n) => n.to_string().yellow(),
-                Err(x) => x.to_string().red(),
-            };
-            let name = match f.0.file_name().map(|f| f.to_string_lossy()) {
-                Some(x) => x.into_owned(),
-                None => "File not UTF-8".to_string(),
-            };
-            format!("{name} [{count}]")
-        },
-    )
+// TODO counts in the directory tree
+pub struct TreeReport(Node<String, DPath>);
+
+impl IntoReport<TreeReport> for Report {
+    type Error = TDError;
+    fn into_report(self, _: &Config) -> Result<TreeReport, Self::Error> {
+        Ok(TreeReport(self.0.map(
+            |d, _| d,
+            |f| {
+                let count = match &f.error {
+                    None if f.todos.is_empty() => "0".green(),
+                    None => f.todos.len().to_string().yellow(),
+                    Some(e) => e.to_string().red(),
+                };
+                let name = match f.from.file_name().map(|f| f.to_string_lossy()) {
+                    Some(x) => x.into_owned(),
+                    None => "File not UTF-8".to_string(),
+                };
+                format!("{name} [{count}]")
+            },
+        )))
+    }
+}
+
+impl fmt::Display for TreeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
 }
 
-// TODO: I'm calling .map/.map_ref (push action, not pull) on the same Node 3 times
 impl fmt::Display for TextReport {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let x = self.0.map_ref(
-            |_, _| (),
-            |fl| {
-                let Ok(fl) = fl else { panic!() };
-                if fl.todos.is_empty() {
-                    return Ok(());
+        for fl in self.0.iter().filter(|fl| !fl.todos.is_empty()) {
+            let name = report_path(&fl.from);
+            for t in &fl.todos {
+                for (n, l) in t.context_before.iter().enumerate() {
+                    let n = t.line - t.context_before.len() + n;
+                    writeln!(f, "{n:>6}| {l}")?;
+                }
+                write!(f, "{name}:{}:{}: {}", t.line, t.col, t.text)?;
+                let meta = t.meta.to_string();
+                if !meta.is_empty() {
+                    write!(f, " {meta}")?;
+                }
+                if let Some(b) = &t.blame {
+                    let commit = &b.commit[..8.min(b.commit.len())];
+                    write!(f, " ({}, {}d ago, {commit})", b.author, b.age_days)?;
                 }
-
-                // skip first directory
-                let name = fl
-                    .from
-                    .iter()
-                    .skip(1)
-                    .collect::<PathBuf>()
-                    .display()
-                    .to_string();
-                f.write_str(&name)?;
                 f.write_char('\n')?;
-                for t in &fl.todos {
-                    f.write_char('\t')?;
-                    f.write_str(&t.text)?;
-                    f.write_char('\n')?;
+                for (n, l) in t.context_after.iter().enumerate() {
+                    writeln!(f, "{:>6}| {l}", t.line + n + 1)?;
                 }
-                f.write_char('\n')
-            },
-        );
-        for a in x.into_atoms() {
-            a?;
+            }
+            f.write_char('\n')?;
         }
         Ok(())
     }
 }
 
+// path as shown in reports, relative to the scanned directory
+fn report_path(from: &Path) -> String {
+    // skip first directory
+    from.iter().skip(1).collect::<PathBuf>().display().to_string()
+}
+
+impl Report {
+    // every scanned file, in walk order. Sorted by age the TODOs of every file
+    // are ordered together, a file is split where another's TODO comes between
+    fn into_files(self) -> Result<Vec<FileTodos>, TDError> {
+        let files = self
+            .0
+            .into_atoms()
+            .into_iter()
+            .map(FileTodos::ok)
+            .collect::<Result<Vec<_>, _>>()?;
+        if self.1 != SortBy::Age {
+            return Ok(files);
+        }
+        let mut todos: Vec<(PathBuf, Todo)> = files
+            .into_iter()
+            .flat_map(|f| {
+                let from = f.from;
+                f.todos.into_iter().map(move |t| (from.clone(), t))
+            })
+            .collect();
+        todos.sort_by_key(|(_, t)| age_key(t));
+        let mut out: Vec<FileTodos> = vec![];
+        for (from, todo) in todos {
+            match out.last_mut() {
+                Some(last) if last.from == from => last.todos.push(todo),
+                _ => out.push(FileTodos {
+                    from,
+                    todos: vec![todo],
+                    error: None,
+                }),
+            }
+        }
+        Ok(out)
+    }
+}
+
+// uncommited TODOs are the newest
+fn age_key(t: &Todo) -> i64 {
+    t.blame.as_ref().map(|b| b.time).unwrap_or(i64::MAX)
+}
+
 impl IntoReport<TextReport> for Report {
     type Error = TDError;
     fn into_report(self: Report, _: &Config) -> Result<TextReport, Self::Error> {
-        Ok(TextReport(self.0))
+        Ok(TextReport(self.into_files()?))
     }
 }
 
@@ -175,23 +310,159 @@ pub trait IntoReport<R> {
     fn into_report(self, cfg: &Config) -> Result<R, Self::Error>;
 }
 
-pub fn make_report<R>(flst: Node<FPath, DPath>, cfg: &Config) -> Result<R, <Report as IntoReport<R>>::Error>
-where Report: IntoReport<R>
+// scan and keep what the options' filters let through, for a single report
+pub fn make_report<R>(
+    flst: Node<FPath, DPath>,
+    cfg: &LayeredConfig,
+    opts: &ReportOptions,
+) -> Result<R, TDError>
+where
+    Report: IntoReport<R, Error = TDError>,
 {
-    Report(flst.map(
-        |d, _| d,
-        |f| {
-            let reports: Vec<Todo> = f
-                .report_todos(cfg)?
-                .into_iter()
-                .map(|(pos, text)| Todo { pos, text })
-                .collect();
-            let todo_entry = FileTodos {
-                from: f.0,
-                todos: reports,
-            };
-            Ok(todo_entry)
-        },
-    )).into_report(cfg)
+    scan(flst, cfg, opts)?.filtered(opts).into_report(cfg)
 }
 
+// read, search and blame every file once, the filters in `opts` aren't applied
+// so reports with and without them can be made from the same scan
+pub fn scan(
+    flst: Node<FPath, DPath>,
+    cfg: &LayeredConfig,
+    opts: &ReportOptions,
+) -> Result<Report, TDError> {
+    // reading and matching is done in parallel, blame needs the repository
+    // which can't be shared between threads
+    let context = opts.context;
+    // staged files are scanned as they are in the index
+    let changes = opts.changes.as_ref();
+    let staged = |f: &FPath| changes.and_then(|c| c.staged(&f.0));
+    let scanned = flst.par_map_atoms(opts.jobs, |f| {
+        let todos = match f.report_todos_in(cfg, context, staged(f)) {
+            Ok(matches) => matches,
+            Err(e) => {
+                return FileTodos {
+                    from: f.0.clone(),
+                    todos: vec![],
+                    error: Some(Arc::new(e)),
+                }
+            }
+        };
+        let todos = todos
+            .into_iter()
+            .map(|m| Todo {
+                pos: m.pos,
+                line: m.line,
+                col: m.col,
+                meta: TodoMeta::parse(&m.text),
+                text: m.text,
+                finder: m.finder,
+                context_before: m.context_before,
+                context_after: m.context_after,
+                blame: None,
+            })
+            .collect();
+        FileTodos {
+            from: f.0.clone(),
+            todos,
+            error: None,
+        }
+    });
+    Ok(Report(
+        scanned.map(
+            |d, _| d,
+            |mut f: FileTodos| {
+                if let (Some(blamer), None) = (&opts.blamer, &f.error) {
+                    let staged = changes.and_then(|c| c.staged(&f.from));
+                    if let Err(e) = blame_todos(&f.from, staged, &mut f.todos, blamer) {
+                        f.error = Some(Arc::new(e));
+                    }
+                }
+                if opts.sort == SortBy::Age {
+                    f.todos.sort_by_key(age_key);
+                }
+                f
+            },
+        ),
+        opts.sort,
+    ))
+}
+
+impl Report {
+    // the TODOs kept by the metadata filter, --older-than and --added-only
+    pub fn filtered(&self, opts: &ReportOptions) -> Report {
+        let filter = &opts.filter;
+        let added = opts.changes.as_ref().filter(|_| opts.added_only);
+        let old_enough = |t: &Todo| match opts.older_than {
+            Some(days) => t.blame.as_ref().is_some_and(|b| b.age_days > days),
+            None => true,
+        };
+        let files = self.0.map_ref(
+            |d, _| d.clone(),
+            |f| FileTodos {
+                todos: f
+                    .todos
+                    .iter()
+                    .filter(|t| filter.matches(&t.meta) && old_enough(t))
+                    .filter(|t| added.is_none_or(|a| a.is_added(&f.from, t.line)))
+                    .cloned()
+                    .collect(),
+                ..f.clone()
+            },
+        );
+        Report(files, self.1)
+    }
+}
+
+fn blame_todos(
+    path: &Path,
+    staged: Option<&[u8]>,
+    todos: &mut [Todo],
+    blamer: &Blamer,
+) -> Result<(), TDError> {
+    if todos.is_empty() {
+        return Ok(());
+    }
+    let lines = todos.iter().map(|t| t.line);
+    let blames = match staged {
+        Some(contents) => blamer.blame_contents(path, contents, lines)?,
+        None => blamer.blame_lines(path, lines)?,
+    };
+    for (todo, blame) in todos.iter_mut().zip(blames) {
+        todo.blame = Some(blame);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filstu::read_dir_filtered;
+    use std::fs;
+
+    #[test]
+    fn unreadable_files_keep_their_path() {
+        let root = std::env::temp_dir().join(format!("todo-rs-scan-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        let conf = root.join(".todo.toml");
+        fs::write(
+            &conf,
+            "read_by_default = true\nsearch_by_default = true\nlanguages = [\"rust\"]\n",
+        )
+        .unwrap();
+        fs::write(root.join("ok.rs"), "// TODO: fine\n").unwrap();
+        fs::write(root.join("bad.rs"), b"// TODO: \xff\n").unwrap();
+
+        let layered = LayeredConfig::new(&root, vec![conf]).unwrap();
+        let flst = read_dir_filtered(&root, &layered).unwrap();
+        let report = scan(flst, &layered, &ReportOptions::default()).unwrap();
+        let cfg = &layered.for_dir(&root).unwrap().cfg;
+        let tree: TreeReport = report.clone().into_report(cfg).unwrap();
+        assert!(tree.to_string().contains("bad.rs ["));
+        let err = report.into_files().unwrap_err();
+        assert!(
+            matches!(&err, TDError::InFile(path, _) if path == &root.join("bad.rs")),
+            "{err}"
+        );
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
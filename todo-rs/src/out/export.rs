@@ -0,0 +1,227 @@
+use super::*;
+use std::collections::HashSet;
+
+// every TODO with a fingerprint that survives line changes, so sinks can
+// update the item they created on a previous run instead of adding another
+pub struct Export(pub Vec<ExportItem>);
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ExportItem {
+    pub fingerprint: String,
+    path: String,
+    #[serde(flatten)]
+    todo: Todo,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ExportSummary {
+    // sinks that can't tell new TODOs from known ones only count what they sent
+    pub sent: usize,
+    pub created: usize,
+    pub updated: usize,
+}
+
+impl fmt::Display for ExportSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.sent {
+            0 => write!(f, "{} created, {} updated", self.created, self.updated),
+            n => write!(f, "{n} sent"),
+        }
+    }
+}
+
+// somewhere TODOs are sent to become tickets
+pub trait ExportSink {
+    fn export(&mut self, items: &[ExportItem]) -> Result<ExportSummary, TDError>;
+}
+
+impl IntoReport<Export> for Report {
+    type Error = TDError;
+    fn into_report(self, _: &Config) -> Result<Export, Self::Error> {
+        let mut items = vec![];
+        for file in self.into_files()? {
+            let path = report_path(&file.from);
+            // the same TODO can appear more than once in a file
+            let mut seen: HashMap<String, usize> = HashMap::new();
+            for todo in file.todos {
+                let key = todo.meta.message.clone().unwrap_or(todo.text.clone());
+                let nth = seen.entry(key.clone()).or_default();
+                let fingerprint = fingerprint(&[&path, formats::tag(&todo), key.trim(), &nth.to_string()]);
+                *nth += 1;
+                items.push(ExportItem {
+                    fingerprint,
+                    path: path.clone(),
+                    todo,
+                });
+            }
+        }
+        Ok(Export(items))
+    }
+}
+
+// FNV-1a, std's hashers aren't guaranteed to be stable between releases
+fn fingerprint(parts: &[&str]) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for part in parts {
+        for byte in part.bytes().chain([0]) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    format!("{hash:016x}")
+}
+
+// POSTs every TODO as a line of JSON, the receiver upserts by fingerprint
+pub struct WebhookSink {
+    pub url: String,
+}
+
+impl ExportSink for WebhookSink {
+    fn export(&mut self, items: &[ExportItem]) -> Result<ExportSummary, TDError> {
+        let mut body = String::new();
+        for item in items {
+            body += &serde_json::to_string(item)?;
+            body.push('\n');
+        }
+        ureq::post(&self.url)
+            .set("Content-Type", "application/x-ndjson")
+            .send_string(&body)
+            .map_err(Box::new)?;
+        Ok(ExportSummary {
+            sent: items.len(),
+            ..Default::default()
+        })
+    }
+}
+
+// one markdown file per TODO, named after its fingerprint
+pub struct IssueStubSink {
+    pub dir: PathBuf,
+}
+
+impl ExportSink for IssueStubSink {
+    fn export(&mut self, items: &[ExportItem]) -> Result<ExportSummary, TDError> {
+        std::fs::create_dir_all(&self.dir)?;
+        let existing: HashSet<PathBuf> = std::fs::read_dir(&self.dir)?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .collect();
+        let mut summary = ExportSummary::default();
+        for item in items {
+            let path = self.dir.join(format!("{}.md", item.fingerprint));
+            match existing.contains(&path) {
+                true => summary.updated += 1,
+                false => summary.created += 1,
+            }
+            std::fs::write(path, item.stub())?;
+        }
+        Ok(summary)
+    }
+}
+
+impl ExportItem {
+    fn stub(&self) -> String {
+        let t = &self.todo;
+        let title = t.meta.message.as_deref().unwrap_or(t.text.trim());
+        let mut out = format!("# {} {title}\n\n", formats::tag(t));
+        out += &format!("- location: `{}:{}:{}`\n", self.path, t.line, t.col);
+        if let Some(owner) = &t.meta.owner {
+            out += &format!("- owner: {owner}\n");
+        }
+        if let Some(issue) = &t.meta.issue {
+            out += &format!("- issue: {issue}\n");
+        }
+        if let Some(priority) = t.meta.priority {
+            out += &format!("- priority: p{priority}\n");
+        }
+        if let Some(due) = &t.meta.due {
+            out += &format!("- due: {due}\n");
+        }
+        if let Some(b) = &t.blame {
+            out += &format!("- author: {} ({})\n", b.author, b.commit);
+        }
+        out += &format!("- fingerprint: {}\n", self.fingerprint);
+        if !t.context_before.is_empty() || !t.context_after.is_empty() {
+            out += "\n```\n";
+            for l in t.context_before.iter().chain([&t.text]).chain(&t.context_after) {
+                out += l;
+                out.push('\n');
+            }
+            out += "```\n";
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    fn item(text: &str) -> ExportItem {
+        ExportItem {
+            fingerprint: fingerprint(&["src/a.rs", text]),
+            path: "src/a.rs".to_string(),
+            todo: Todo {
+                pos: 0,
+                line: 1,
+                col: 1,
+                text: text.to_string(),
+                finder: "rust".to_string(),
+                meta: TodoMeta::parse(text),
+                context_before: vec![],
+                context_after: vec![],
+                blame: None,
+            },
+        }
+    }
+
+    #[test]
+    fn fingerprints_are_stable() {
+        assert_eq!(fingerprint(&["a", "b"]), fingerprint(&["a", "b"]));
+        assert_ne!(fingerprint(&["ab", ""]), fingerprint(&["a", "b"]));
+        assert_eq!(fingerprint(&[]), "cbf29ce484222325");
+    }
+
+    #[test]
+    fn webhook_posts_json_lines() {
+        let server = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/todos", server.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let (stream, _) = server.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if let Some(l) = line.to_lowercase().strip_prefix("content-length:") {
+                    length = l.trim().parse().unwrap();
+                }
+                if line == "\r\n" {
+                    break;
+                }
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            let mut stream = reader.into_inner();
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                .unwrap();
+            String::from_utf8(body).unwrap()
+        });
+
+        let items = [item("TODO(bob): one"), item("FIXME: two")];
+        let mut sink = WebhookSink { url };
+        sink.export(&items).unwrap();
+
+        let body = handle.join().unwrap();
+        let lines: Vec<serde_json::Value> = body
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["fingerprint"], items[0].fingerprint);
+        assert_eq!(lines[0]["owner"], "bob");
+    }
+}
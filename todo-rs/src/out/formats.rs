@@ -0,0 +1,279 @@
+use super::*;
+use serde_json::{json, Value};
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+const DEFAULT_TAG: &str = "TODO";
+
+// SARIF 2.1.0 log, each TODO is a result so it shows up as a code-scanning annotation
+#[derive(Serialize)]
+pub struct SARIFReport(Value);
+// checklist grouped by file
+pub struct MarkdownReport(Vec<FileTodos>);
+pub struct CSVReport(Vec<FileTodos>);
+
+impl IntoReport<SARIFReport> for Report {
+    type Error = TDError;
+    fn into_report(self, _: &Config) -> Result<SARIFReport, Self::Error> {
+        let files = self.into_files()?;
+        let todos = || files.iter().flat_map(|f| f.todos.iter().map(move |t| (f, t)));
+
+        let mut tags: Vec<&str> = todos().map(|(_, t)| tag(t)).collect();
+        tags.sort();
+        tags.dedup();
+        let rules: Vec<Value> = tags
+            .iter()
+            .map(|tag| {
+                json!({
+                    "id": tag,
+                    "shortDescription": { "text": format!("{tag} comment") },
+                })
+            })
+            .collect();
+
+        let results: Vec<Value> = todos()
+            .map(|(f, t)| {
+                let mut properties = serde_json::to_value(&t.meta)?;
+                if let (Some(b), Value::Object(props)) = (&t.blame, &mut properties) {
+                    props.insert("author".into(), b.author.clone().into());
+                    props.insert("commit".into(), b.commit.clone().into());
+                    props.insert("age_days".into(), b.age_days.into());
+                }
+                Ok(json!({
+                    "ruleId": tag(t),
+                    "level": "note",
+                    "message": { "text": t.text },
+                    "locations": [{
+                        "physicalLocation": {
+                            "artifactLocation": { "uri": uri_path(&f.from) },
+                            "region": { "startLine": t.line, "startColumn": t.col },
+                        }
+                    }],
+                    "properties": properties,
+                }))
+            })
+            .collect::<Result<_, TDError>>()?;
+
+        Ok(SARIFReport(json!({
+            "$schema": SARIF_SCHEMA,
+            "version": "2.1.0",
+            "runs": [{
+                // columns are counted in chars, not SARIF's default UTF-16 units
+                "columnKind": "unicodeCodePoints",
+                "tool": {
+                    "driver": {
+                        "name": env!("CARGO_PKG_NAME"),
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "rules": rules,
+                    }
+                },
+                "results": results,
+            }],
+        })))
+    }
+}
+
+impl IntoReport<MarkdownReport> for Report {
+    type Error = TDError;
+    fn into_report(self, _: &Config) -> Result<MarkdownReport, Self::Error> {
+        Ok(MarkdownReport(self.into_files()?))
+    }
+}
+
+impl IntoReport<CSVReport> for Report {
+    type Error = TDError;
+    fn into_report(self, _: &Config) -> Result<CSVReport, Self::Error> {
+        Ok(CSVReport(self.into_files()?))
+    }
+}
+
+impl fmt::Display for MarkdownReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for fl in self.0.iter().filter(|fl| !fl.todos.is_empty()) {
+            let name = report_path(&fl.from);
+            writeln!(f, "## {name}\n")?;
+            for t in &fl.todos {
+                write!(f, "- [ ] [{name}:{}]({name}#L{}) {}", t.line, t.line, t.text.trim())?;
+                let meta = t.meta.to_string();
+                if !meta.is_empty() {
+                    write!(f, " {meta}")?;
+                }
+                if let Some(b) = &t.blame {
+                    write!(f, " ({}, {}d ago)", b.author, b.age_days)?;
+                }
+                f.write_char('\n')?;
+            }
+            f.write_char('\n')?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for CSVReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "path,line,col,tag,owner,issue,priority,due,author,commit,age_days,text"
+        )?;
+        for fl in &self.0 {
+            let name = report_path(&fl.from);
+            for t in &fl.todos {
+                let blame = t.blame.as_ref();
+                let row = [
+                    name.clone(),
+                    t.line.to_string(),
+                    t.col.to_string(),
+                    t.meta.tag.clone().unwrap_or_default(),
+                    t.meta.owner.clone().unwrap_or_default(),
+                    t.meta.issue.clone().unwrap_or_default(),
+                    t.meta.priority.map(|p| p.to_string()).unwrap_or_default(),
+                    t.meta.due.clone().unwrap_or_default(),
+                    blame.map(|b| b.author.clone()).unwrap_or_default(),
+                    blame.map(|b| b.commit.clone()).unwrap_or_default(),
+                    blame.map(|b| b.age_days.to_string()).unwrap_or_default(),
+                    t.text.clone(),
+                ];
+                let row: Vec<String> = row.iter().map(|c| csv_field(c)).collect();
+                writeln!(f, "{}", row.join(","))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub(super) fn tag(t: &Todo) -> &str {
+    t.meta.tag.as_deref().unwrap_or(DEFAULT_TAG)
+}
+
+// RFC 4180 quoting
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+// the report path as a relative URI reference, percent-encoding every byte
+// that isn't an RFC 3986 path character. ':' is encoded too, so the first
+// segment can't read as a scheme
+fn uri_path(from: &Path) -> String {
+    let path = report_path(from);
+    let segments = Path::new(&path).iter().map(|segment| {
+        let mut out = String::new();
+        for b in segment.to_string_lossy().bytes() {
+            match b {
+                b if b.is_ascii_alphanumeric() || b"-._~!$&'()*+,;=@".contains(&b) => {
+                    out.push(b as char)
+                }
+                b => out += &format!("%{b:02X}"),
+            }
+        }
+        out
+    });
+    segments.collect::<Vec<_>>().join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filstu::DPath;
+
+    fn file(path: &str, todos: &[(usize, usize, &str)]) -> FileTodos {
+        let todos = todos
+            .iter()
+            .map(|&(line, col, text)| Todo {
+                pos: 0,
+                line,
+                col,
+                text: text.to_string(),
+                finder: "rust".to_string(),
+                meta: TodoMeta::parse(text),
+                context_before: vec![],
+                context_after: vec![],
+                blame: None,
+            })
+            .collect();
+        FileTodos {
+            from: PathBuf::from(path),
+            todos,
+            error: None,
+        }
+    }
+
+    fn files() -> Vec<FileTodos> {
+        vec![
+            file("./src/a.rs", &[(3, 5, r#"TODO(bob): say "hi", then go"#)]),
+            file("./src/empty.rs", &[]),
+            file("./src/é.rs", &[(1, 4, "FIXME[#2] ünïcode")]),
+        ]
+    }
+
+    #[test]
+    fn sarif_uris() {
+        assert_eq!(
+            uri_path(Path::new("./src/my file#2%.rs")),
+            "src/my%20file%232%25.rs"
+        );
+        assert_eq!(uri_path(Path::new("./a:b/c(1).rs")), "a%3Ab/c(1).rs");
+    }
+
+    #[test]
+    fn csv_quotes_fields() {
+        let csv = CSVReport(files()).to_string();
+        let rows: Vec<&str> = csv.lines().collect();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(
+            rows[1],
+            r#"src/a.rs,3,5,TODO,bob,,,,,,,"TODO(bob): say ""hi"", then go""#
+        );
+        assert_eq!(rows[2], "src/é.rs,1,4,FIXME,,#2,,,,,,FIXME[#2] ünïcode");
+        assert_eq!(csv_field("a\nb"), "\"a\nb\"");
+        assert_eq!(csv_field("a\rb"), "\"a\rb\"");
+    }
+
+    #[test]
+    fn markdown_checklist() {
+        assert_eq!(
+            MarkdownReport(files()).to_string(),
+            r#"## src/a.rs
+
+- [ ] [src/a.rs:3](src/a.rs#L3) TODO(bob): say "hi", then go [@bob]
+
+## src/é.rs
+
+- [ ] [src/é.rs:1](src/é.rs#L1) FIXME[#2] ünïcode [#2]
+
+"#
+        );
+    }
+
+    #[test]
+    fn sarif_results() {
+        let nodes = files().into_iter().map(Node::Atom).collect();
+        let report = Report(Node::List(DPath(".".into()), nodes), SortBy::Position);
+        let raw = crate::conf::ConfigRaw {
+            read_by_default: Some(true),
+            search_by_default: Some(true),
+            ..Default::default()
+        };
+        let cfg = Config::try_from(raw).unwrap();
+        let SARIFReport(sarif): SARIFReport = report.into_report(&cfg).unwrap();
+        let run = &sarif["runs"][0];
+        assert_eq!(run["columnKind"], "unicodeCodePoints");
+        let rules: Vec<&Value> = run["tool"]["driver"]["rules"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| &r["id"])
+            .collect();
+        assert_eq!(rules, ["FIXME", "TODO"]);
+        let results = run["results"].as_array().unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0]["ruleId"], "TODO");
+        assert_eq!(results[0]["properties"]["owner"], "bob");
+        let location = &results[1]["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "src/%C3%A9.rs");
+        assert_eq!(location["region"]["startLine"], 1);
+        assert_eq!(location["region"]["startColumn"], 4);
+    }
+}
@@ -0,0 +1,66 @@
+use super::*;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+const NO_EXTENSION: &str = "(none)";
+const NO_OWNER: &str = "(unassigned)";
+
+// TODO counts, each TODO is counted once in every breakdown
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct StatsReport {
+    pub total: usize,
+    pub by_dir: BTreeMap<String, usize>,
+    pub by_finder: BTreeMap<String, usize>,
+    pub by_ext: BTreeMap<String, usize>,
+    pub by_owner: BTreeMap<String, usize>,
+}
+
+impl IntoReport<StatsReport> for Report {
+    type Error = TDError;
+    fn into_report(self, _: &Config) -> Result<StatsReport, Self::Error> {
+        let mut stats = StatsReport::default();
+        for file in self.into_files()? {
+            let path = report_path(&file.from);
+            let dir = match path.rsplit_once('/') {
+                Some((dir, _)) => dir.to_string(),
+                None => ".".to_string(),
+            };
+            let ext = file
+                .from
+                .extension()
+                .map_or(NO_EXTENSION.to_string(), |e| e.to_string_lossy().into_owned());
+            for todo in &file.todos {
+                stats.total += 1;
+                *stats.by_dir.entry(dir.clone()).or_default() += 1;
+                *stats.by_finder.entry(todo.finder.clone()).or_default() += 1;
+                *stats.by_ext.entry(ext.clone()).or_default() += 1;
+                let owner = todo.meta.owner.as_deref().unwrap_or(NO_OWNER);
+                *stats.by_owner.entry(owner.to_string()).or_default() += 1;
+            }
+        }
+        Ok(stats)
+    }
+}
+
+impl fmt::Display for StatsReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} TODOs", self.total)?;
+        let sections = [
+            ("directory", &self.by_dir),
+            ("finder", &self.by_finder),
+            ("extension", &self.by_ext),
+            ("owner", &self.by_owner),
+        ];
+        for (name, counts) in sections {
+            writeln!(f, "\nby {name}")?;
+            // most TODOs first
+            let mut counts: Vec<_> = counts.iter().collect();
+            counts.sort_by(|(a, an), (b, bn)| bn.cmp(an).then(a.cmp(b)));
+            let width = counts.first().map_or(1, |(_, n)| n.to_string().len());
+            for (key, n) in counts {
+                writeln!(f, "  {n:>width$}  {key}")?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,501 @@
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::Command;
+
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Print, Stylize};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+
+use crate::conf::LayeredConfig;
+use crate::filstu::{DPath, FPath, Node};
+use crate::meta::TodoMeta;
+use crate::{TDError, TodoMatch};
+
+const HELP: &str = "j/k move  enter open  h close  / text  @ owner  esc clear  e edit  q quit";
+
+// a file or directory of the scanned tree
+struct Tree {
+    name: String,
+    path: PathBuf,
+    open: bool,
+    kind: Kind,
+}
+
+enum Kind {
+    Dir(Vec<Tree>),
+    // the error if the file couldn't be read
+    File(Result<Vec<Item>, String>),
+}
+
+// files with the TODOs found in them
+type Scanned = Node<(PathBuf, Result<Vec<TodoMatch>, TDError>), DPath>;
+
+struct Item {
+    todo: TodoMatch,
+    meta: TodoMeta,
+}
+
+// a visible line, nodes are addressed by their child indices from the root
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Row {
+    Node(Vec<usize>),
+    Todo(Vec<usize>, usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Input {
+    Text,
+    Owner,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Action {
+    Redraw,
+    Quit,
+    Edit(PathBuf, usize),
+}
+
+struct Browser {
+    root: Tree,
+    rows: Vec<(usize, Row)>,
+    selected: usize,
+    text: String,
+    owner: String,
+    // which filter is being typed
+    input: Option<Input>,
+}
+
+// scan the tree and browse it until the user quits
+pub fn browse(
+    flst: Node<FPath, DPath>,
+    cfg: &LayeredConfig,
+    context: usize,
+    jobs: usize,
+    owner: Option<String>,
+) -> Result<(), TDError> {
+    let scanned = flst.par_map_atoms(jobs, |f| (f.0.clone(), f.report_todos(cfg, context)));
+    let mut browser = Browser::new(scanned);
+    browser.owner = owner.unwrap_or_default();
+    browser.refresh();
+
+    let mut term = Terminal::enter()?;
+    loop {
+        browser.draw(&mut term.out)?;
+        let Event::Key(key) = event::read()? else { continue };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match browser.key(key) {
+            Action::Redraw => (),
+            Action::Quit => return Ok(()),
+            Action::Edit(path, line) => {
+                term.leave()?;
+                open_editor(&path, line)?;
+                term = Terminal::enter()?;
+            }
+        }
+    }
+}
+
+// `$EDITOR +LINE PATH`, the convention vi, emacs, nano and most others follow
+fn open_editor(path: &std::path::Path, line: usize) -> Result<(), TDError> {
+    let editor = std::env::var("VISUAL")
+        .or(std::env::var("EDITOR"))
+        .unwrap_or("vi".to_string());
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or("vi");
+    Command::new(program)
+        .args(words)
+        .arg(format!("+{line}"))
+        .arg(path)
+        .status()?;
+    Ok(())
+}
+
+// raw mode on the alternate screen, restored when dropped
+struct Terminal {
+    out: io::Stdout,
+    active: bool,
+}
+
+impl Terminal {
+    fn enter() -> io::Result<Self> {
+        let mut out = io::stdout();
+        terminal::enable_raw_mode()?;
+        execute!(out, EnterAlternateScreen, Hide)?;
+        Ok(Terminal { out, active: true })
+    }
+
+    fn leave(&mut self) -> io::Result<()> {
+        if self.active {
+            self.active = false;
+            execute!(self.out, Show, LeaveAlternateScreen)?;
+            terminal::disable_raw_mode()?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let _ = self.leave();
+    }
+}
+
+impl Tree {
+    fn new(node: Scanned) -> Tree {
+        let (path, kind) = match node {
+            Node::Atom((path, todos)) => {
+                let items = todos.map_err(|e| e.to_string()).map(|todos| {
+                    todos
+                        .into_iter()
+                        .map(|todo| Item {
+                            meta: TodoMeta::parse(&todo.text),
+                            todo,
+                        })
+                        .collect()
+                });
+                (path, Kind::File(items))
+            }
+            Node::List(DPath(path), children) => {
+                (path, Kind::Dir(children.into_iter().map(Tree::new).collect()))
+            }
+        };
+        let name = path
+            .file_name()
+            .map_or(path.to_string_lossy(), |n| n.to_string_lossy())
+            .into_owned();
+        Tree {
+            name,
+            path,
+            open: false,
+            kind,
+        }
+    }
+
+    fn get(&self, at: &[usize]) -> &Tree {
+        match (at, &self.kind) {
+            ([], _) => self,
+            ([i, rest @ ..], Kind::Dir(children)) => children[*i].get(rest),
+            _ => panic!("row points into a file"),
+        }
+    }
+
+    fn get_mut(&mut self, at: &[usize]) -> &mut Tree {
+        let Some((i, rest)) = at.split_first() else { return self };
+        match &mut self.kind {
+            Kind::Dir(children) => children[*i].get_mut(rest),
+            Kind::File(_) => panic!("row points into a file"),
+        }
+    }
+
+    fn open_all(&mut self) {
+        self.open = true;
+        if let Kind::Dir(children) = &mut self.kind {
+            children.iter_mut().for_each(Tree::open_all);
+        }
+    }
+}
+
+impl Browser {
+    fn new(scanned: Scanned) -> Browser {
+        let mut root = Tree::new(scanned);
+        root.open = true;
+        let mut browser = Browser {
+            root,
+            rows: vec![],
+            selected: 0,
+            text: String::new(),
+            owner: String::new(),
+            input: None,
+        };
+        browser.refresh();
+        browser
+    }
+
+    fn matches(&self, item: &Item) -> bool {
+        let contains = |hay: &str, needle: &str| hay.to_lowercase().contains(&needle.to_lowercase());
+        let text = self.text.is_empty() || contains(&item.todo.text, &self.text);
+        let owner = self.owner.is_empty()
+            || item.meta.owner.as_deref().is_some_and(|o| contains(o, &self.owner));
+        text && owner
+    }
+
+    // TODOs under `tree` that pass the filters, unreadable files count so they stay visible
+    fn count(&self, tree: &Tree) -> usize {
+        match &tree.kind {
+            Kind::Dir(children) => children.iter().map(|c| self.count(c)).sum(),
+            Kind::File(Ok(items)) => items.iter().filter(|i| self.matches(i)).count(),
+            Kind::File(Err(_)) => 1,
+        }
+    }
+
+    // rebuild the visible rows, keeping the selection in range
+    fn refresh(&mut self) {
+        let mut rows = vec![];
+        self.push_rows(&self.root, vec![], 0, &mut rows);
+        self.rows = rows;
+        self.selected = self.selected.min(self.rows.len().saturating_sub(1));
+    }
+
+    fn push_rows(&self, tree: &Tree, at: Vec<usize>, depth: usize, rows: &mut Vec<(usize, Row)>) {
+        // the root itself isn't shown
+        if !at.is_empty() {
+            if self.count(tree) == 0 {
+                return;
+            }
+            rows.push((depth, Row::Node(at.clone())));
+        }
+        if !tree.open {
+            return;
+        }
+        let depth = if at.is_empty() { 0 } else { depth + 1 };
+        match &tree.kind {
+            Kind::Dir(children) => {
+                for (i, child) in children.iter().enumerate() {
+                    let mut at = at.clone();
+                    at.push(i);
+                    self.push_rows(child, at, depth, rows);
+                }
+            }
+            Kind::File(Ok(items)) => {
+                for (i, item) in items.iter().enumerate() {
+                    if self.matches(item) {
+                        rows.push((depth, Row::Todo(at.clone(), i)));
+                    }
+                }
+            }
+            Kind::File(Err(_)) => (),
+        }
+    }
+
+    fn item(&self, at: &[usize], i: usize) -> &Item {
+        match &self.root.get(at).kind {
+            Kind::File(Ok(items)) => &items[i],
+            _ => panic!("TODO row outside of a file"),
+        }
+    }
+
+    fn key(&mut self, key: KeyEvent) -> Action {
+        if let Some(input) = self.input {
+            let filter = match input {
+                Input::Text => &mut self.text,
+                Input::Owner => &mut self.owner,
+            };
+            match key.code {
+                KeyCode::Char(c) => filter.push(c),
+                KeyCode::Backspace => {
+                    filter.pop();
+                }
+                KeyCode::Enter => {
+                    self.input = None;
+                    self.root.open_all();
+                }
+                KeyCode::Esc => {
+                    filter.clear();
+                    self.input = None;
+                }
+                _ => (),
+            }
+            self.refresh();
+            return Action::Redraw;
+        }
+
+        let row = self.rows.get(self.selected).map(|(_, r)| r.clone());
+        match (key.code, row) {
+            (KeyCode::Char('c'), _) if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return Action::Quit
+            }
+            (KeyCode::Char('q'), _) => return Action::Quit,
+            (KeyCode::Char('j') | KeyCode::Down, _) => {
+                self.selected = (self.selected + 1).min(self.rows.len().saturating_sub(1))
+            }
+            (KeyCode::Char('k') | KeyCode::Up, _) => self.selected = self.selected.saturating_sub(1),
+            (KeyCode::Char('g') | KeyCode::Home, _) => self.selected = 0,
+            (KeyCode::Char('G') | KeyCode::End, _) => {
+                self.selected = self.rows.len().saturating_sub(1)
+            }
+            (KeyCode::Char('/'), _) => self.input = Some(Input::Text),
+            (KeyCode::Char('@'), _) => self.input = Some(Input::Owner),
+            (KeyCode::Esc, _) => {
+                self.text.clear();
+                self.owner.clear();
+            }
+            (KeyCode::Enter | KeyCode::Char('l') | KeyCode::Right | KeyCode::Char(' '), Some(Row::Node(at))) => {
+                let node = self.root.get_mut(&at);
+                node.open = !node.open || key.code == KeyCode::Right || key.code == KeyCode::Char('l');
+            }
+            (KeyCode::Char('h') | KeyCode::Left, Some(row)) => {
+                let at = match row {
+                    Row::Node(at) if self.root.get(&at).open => at,
+                    // close the parent and select it
+                    Row::Node(mut at) => {
+                        at.pop();
+                        at
+                    }
+                    Row::Todo(at, _) => at,
+                };
+                if !at.is_empty() {
+                    self.root.get_mut(&at).open = false;
+                    self.refresh();
+                    self.selected = self
+                        .rows
+                        .iter()
+                        .position(|(_, r)| *r == Row::Node(at.clone()))
+                        .unwrap_or(0);
+                }
+            }
+            (KeyCode::Enter | KeyCode::Char('e'), Some(Row::Todo(at, i))) => {
+                let path = self.root.get(&at).path.clone();
+                return Action::Edit(path, self.item(&at, i).todo.line);
+            }
+            _ => (),
+        }
+        self.refresh();
+        Action::Redraw
+    }
+
+    fn draw(&self, out: &mut impl Write) -> io::Result<()> {
+        let (width, height) = terminal::size()?;
+        let (width, height) = (width as usize, height as usize);
+        let fit = |s: &str| s.chars().take(width).collect::<String>();
+
+        queue!(out, Clear(ClearType::All), MoveTo(0, 0))?;
+        let mut filters = String::new();
+        if !self.text.is_empty() || self.input == Some(Input::Text) {
+            filters += &format!("  /{}", self.text);
+        }
+        if !self.owner.is_empty() || self.input == Some(Input::Owner) {
+            filters += &format!("  @{}", self.owner);
+        }
+        let todos = self.count(&self.root);
+        queue!(out, Print(fit(&format!("todo-rs [{todos}]{filters}")).bold()))?;
+
+        // the selected TODO's context goes below the list
+        let context = match self.rows.get(self.selected) {
+            Some((_, Row::Todo(at, i))) => self.context_lines(at, *i),
+            _ => vec![],
+        };
+        let list_height = height.saturating_sub(context.len() + 3);
+        let offset = (self.selected + 1).saturating_sub(list_height);
+        for (n, (depth, row)) in self.rows.iter().enumerate().skip(offset).take(list_height) {
+            let indent = "  ".repeat(*depth);
+            let line = match row {
+                Row::Node(at) => {
+                    let tree = self.root.get(at);
+                    let mark = match (&tree.kind, tree.open) {
+                        (Kind::File(Err(e)), _) => format!("! {e}"),
+                        (_, true) => "v".to_string(),
+                        (_, false) => ">".to_string(),
+                    };
+                    format!("{indent}{mark} {} [{}]", tree.name, self.count(tree))
+                }
+                Row::Todo(at, i) => {
+                    let item = self.item(at, *i);
+                    format!("{indent}{:>4}: {}", item.todo.line, item.todo.text.trim())
+                }
+            };
+            let line = fit(&line);
+            let y = (n - offset + 1) as u16;
+            match n == self.selected {
+                true => queue!(out, MoveTo(0, y), Print(line.reverse()))?,
+                false => queue!(out, MoveTo(0, y), Print(line))?,
+            }
+        }
+
+        let top = height.saturating_sub(context.len() + 1);
+        for (n, line) in context.iter().enumerate() {
+            queue!(out, MoveTo(0, (top + n) as u16), Print(fit(line).dim()))?;
+        }
+        let status = match self.input {
+            Some(Input::Text) => "text filter, enter to apply, esc to clear",
+            Some(Input::Owner) => "owner filter, enter to apply, esc to clear",
+            None => HELP,
+        };
+        queue!(
+            out,
+            MoveTo(0, height.saturating_sub(1) as u16),
+            Print(fit(status).dim())
+        )?;
+        out.flush()
+    }
+
+    // metadata, then the TODO's line between its context lines
+    fn context_lines(&self, at: &[usize], i: usize) -> Vec<String> {
+        let Item { todo, meta } = self.item(at, i);
+        let first = todo.line - todo.context_before.len();
+        let mut out: Vec<String> = Some(meta.to_string()).filter(|m| !m.is_empty()).into_iter().collect();
+        for (n, l) in todo.context_before.iter().enumerate() {
+            out.push(format!("{:>5} | {l}", first + n));
+        }
+        out.push(format!("{:>5} > {}", todo.line, todo.text));
+        for (n, l) in todo.context_after.iter().enumerate() {
+            out.push(format!("{:>5} | {l}", todo.line + 1 + n));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn todo(line: usize, text: &str) -> TodoMatch {
+        TodoMatch {
+            pos: 0,
+            line,
+            col: 1,
+            text: text.to_string(),
+            finder: "rust".to_string(),
+            context_before: vec![],
+            context_after: vec![],
+        }
+    }
+
+    fn press(browser: &mut Browser, keys: &str) -> Action {
+        let mut action = Action::Redraw;
+        for c in keys.chars() {
+            let code = match c {
+                '\n' => KeyCode::Enter,
+                c => KeyCode::Char(c),
+            };
+            action = browser.key(KeyEvent::from(code));
+        }
+        action
+    }
+
+    #[test]
+    fn filters_and_opens() {
+        let file = |name: &str, todos| Node::Atom((PathBuf::from(name), Ok(todos)));
+        let scanned = Node::List(
+            DPath(".".into()),
+            vec![Node::List(
+                DPath("./src".into()),
+                vec![
+                    file("./src/a.rs", vec![todo(3, "TODO(alice): a"), todo(9, "FIXME: b")]),
+                    file("./src/b.rs", vec![]),
+                ],
+            )],
+        );
+        let mut browser = Browser::new(scanned);
+        // b.rs has no TODOs so it's hidden
+        assert_eq!(press(&mut browser, "\nj\n"), Action::Redraw);
+        assert_eq!(browser.rows.len(), 4);
+
+        press(&mut browser, "@ali\n");
+        assert_eq!(browser.rows.len(), 3);
+        assert_eq!(
+            press(&mut browser, "jj\n"),
+            Action::Edit("./src/a.rs".into(), 3)
+        );
+
+        // esc while typing clears that filter
+        press(&mut browser, "@");
+        browser.key(KeyEvent::from(KeyCode::Esc));
+        assert_eq!(browser.owner, "");
+        press(&mut browser, "/nothing\n");
+        assert!(browser.rows.is_empty());
+    }
+}